@@ -0,0 +1,658 @@
+//! Parsing of the `#[typemap(...)]` attributes, and of the older `#[typemap_...]` ones.
+
+use quote::ToTokens;
+use syn::parse::ParseStream;
+use syn::punctuated::Punctuated;
+use syn::{
+    Attribute, Error, Ident, Lit, LitInt, LitStr, Meta, MetaNameValue, NestedMeta, Path, Result,
+    Token, Type, WherePredicate,
+};
+
+/// Options applying to the whole typemap
+#[derive(Default)]
+pub(crate) struct ContainerOptions {
+    /// Generate mutable accessors, `mut` or `#[typemap_mut]`, or not, the default, with
+    /// `readonly` or `#[typemap_readonly]`
+    pub mutable: bool,
+    /// Prevent downstream crates from adding Get impls, `sealed`
+    pub sealed: bool,
+    /// Implement `AsBytes`, `bytes` or `#[typemap_bytes]`
+    pub bytes: bool,
+    /// Implement `MemUsage`, `mem_usage`
+    pub mem_usage: bool,
+    /// Require a stable layout and implement `FfiSafe`, `ffi`
+    pub ffi: bool,
+    /// Generate `extracted_from`, cloning the fields from a larger typemap, `extract`
+    pub extract: bool,
+    /// Generate `clear_all` and `reset`, `reset`
+    pub reset: bool,
+    /// Implement `AnyTypemap` and other run-time accesses, `any`
+    pub any: bool,
+    /// Implement `Fingerprint`, `fingerprint`
+    pub fingerprint: bool,
+    /// Implement `Display` as a single-line summary, `display`
+    pub display: bool,
+    /// Generate a companion struct with interior mutability, `cell`
+    pub cell: bool,
+    /// Generate `freeze`, turning the struct into a shared immutable one, `freeze`
+    pub freeze: bool,
+    /// Generate `put`, a chainable setter, `put`
+    pub put: bool,
+    /// Generate the generic `get` and `get_mut` methods, `inherent`
+    pub inherent: bool,
+    /// Implement `GetAt`, accessing the fields by position, `positional`
+    pub positional: bool,
+    /// Implement the `Typemap` umbrella trait, `types`
+    pub types: bool,
+    /// Implement `GetPinMut`, pinning the fields structurally, `pin` or `#[typemap_pin]`
+    pub pin: bool,
+    /// Implement `GetDisjointMut` for all the pairs of mutable fields, `disjoint`
+    pub disjoint: bool,
+    /// Implement `QueryField` for the fields, allowing to borrow several of them at once, `query`
+    pub query: bool,
+    /// Generate `accept`, calling a `TypemapVisitor` on all the fields, `visit`
+    pub visit: bool,
+    /// Generate `split_mut`, mutably borrowing all the mutable fields at once, `split`
+    pub split: bool,
+    /// Generate a `{Name}Ref` view struct borrowing the fields, and `as_view`, `view`
+    pub view: bool,
+    /// Generate a `{Name}Mut` view struct mutably borrowing the fields, and `as_view_mut`, `view_mut`
+    pub view_mut: bool,
+    /// Name of the trait requiring `Get` for all the field types, `alias(Name)` or `#[typemap_alias(Name)]`
+    pub alias: Option<Ident>,
+    /// Implement `AsRef` and `AsMut` for the field types, `asref` or `#[typemap_asref]`
+    pub asref: bool,
+    /// Implement `Borrow` and `BorrowMut` for the field types, `borrow` or `#[typemap_borrow]`
+    pub borrow: bool,
+    /// Implement the conversions of references to the struct into references to the field types, `into_ref`
+    pub into_ref: bool,
+    /// Implement the conversions from and to the tuple of the field types, `tuple`
+    pub tuple: bool,
+    /// Implement the conversions into other typemaps, matching the fields by type, `into(Target, ...)`
+    pub into: Vec<Path>,
+    /// Traits to visit the fields as trait objects of, `dyn(Trait, ...)` or `#[typemap_dyn(Trait, ...)]`
+    pub dyn_traits: Vec<Path>,
+    /// Visit the fields as trait objects in parallel, `rayon`
+    pub rayon: bool,
+    /// Implement `Commands` with the given derives on the command enum, `commands(Derive, ...)`
+    pub commands: Option<Vec<Path>>,
+    /// Implement serde's `Serialize` and `Deserialize`, `serde`
+    pub serde: bool,
+    /// Generate the registration in the rhai scripting engine, `rhai`
+    pub rhai: bool,
+    /// Generate conversions from and to Bevy resources, `bevy`
+    pub bevy: bool,
+    /// Generate conversions from and to frunk HLists, `frunk`
+    pub frunk: bool,
+    /// Generate the conversion into a runtime `type_map::TypeMap`, `type_map`
+    pub type_map: bool,
+    /// Maximum size of the struct in bytes, checked at compile time, `max_size = N`
+    pub max_size: Option<LitInt>,
+    /// Concrete instantiations of a generic struct, for items requiring them, `instantiate("...", ...)`
+    pub instantiate: Vec<Type>,
+    /// Additional predicates of the where clauses of the generated items, `bound = "..."`
+    pub bound: Vec<WherePredicate>,
+    /// Path to the typemap-meta crate in generated code, `crate = "..."`
+    pub krate: Option<Path>,
+}
+
+/// Options applying to a single field
+#[derive(Default)]
+pub(crate) struct FieldOptions {
+    /// Do not expose this field, `skip` or `#[typemap_skip]`
+    pub skip: bool,
+    /// Expose this field even if it is a `PhantomData` marker, `include`
+    pub include: bool,
+    /// Override the mutability of the struct for this field, `mut` or `#[typemap_mut]`, or `readonly`
+    pub mutable: Option<bool>,
+    /// Expose the types of the typemap in this field instead of its own, `flatten` or `#[typemap_flatten]`
+    pub flatten: bool,
+    /// Only access this field by value using volatile operations, `volatile` or `#[typemap_volatile]`
+    pub volatile: bool,
+    /// The field is declared as `Box<T>` but accessed as `T`, `boxed`
+    pub boxed: bool,
+    /// The field is declared as `CachePadded<T>` but accessed as `T`, `pad`
+    pub pad: bool,
+    /// The field is an atomic, loaded and stored by the type of its value, `atomic`
+    pub atomic: bool,
+    /// The field is an `embassy-sync` mutex written without its `embassy_sync` path, `embassy`
+    pub embassy: bool,
+    /// Wrap the field in a `RefCell` instead of a `Cell` in the companion struct, `refcell`
+    pub refcell: bool,
+    /// How to serialize this field, `serde(...)`
+    pub serde: SerdeFieldOptions,
+    /// Maximum size of the field in bytes, checked at compile time, `max_size = N`
+    pub max_size: Option<LitInt>,
+    /// Value of the field restored by `reset` instead of the default one, `default = "..."`
+    pub default: Option<proc_macro2::TokenStream>,
+    /// Environment variable the field is read from by `from_env`, `env = "..."`
+    pub env: Option<String>,
+    /// Function parsing the environment variable of the field, `parse_with = "..."`
+    pub parse_with: Option<Path>,
+}
+
+/// Options of the serde integration for a single field
+#[derive(Default)]
+pub(crate) struct SerdeFieldOptions {
+    /// Name of the field in the serialized form instead of its type name, `rename = "..."`
+    pub rename: Option<String>,
+    /// Module providing `serialize` and `deserialize` functions for this field, `with = "..."`
+    pub with: Option<Path>,
+    /// Do not serialize this field, and use its default value when deserializing, `skip`
+    pub skip: bool,
+    /// Use the default value of this field when it is missing while deserializing, `default`
+    pub default: bool,
+}
+
+impl ContainerOptions {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut options = Self::default();
+        let (mut mutable, mut readonly) = (false, false);
+        for attr in attrs {
+            if attr.path.is_ident("typemap_mut") {
+                mutable = true;
+            } else if attr.path.is_ident("typemap_readonly") {
+                readonly = true;
+            } else if attr.path.is_ident("typemap_bytes") {
+                options.bytes = true;
+            } else if attr.path.is_ident("typemap_asref") {
+                options.asref = true;
+            } else if attr.path.is_ident("typemap_borrow") {
+                options.borrow = true;
+            } else if attr.path.is_ident("typemap_pin") {
+                options.pin = true;
+            } else if attr.path.is_ident("typemap_alias") {
+                options.alias = Some(attr.parse_args().map_err(|_| {
+                    Error::new_spanned(
+                        attr,
+                        "Typemap attribute `typemap_alias` expects a trait name, like `#[typemap_alias(Deps)]`!",
+                    )
+                })?);
+            } else if attr.path.is_ident("typemap_dyn") {
+                let traits = attr
+                    .parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)
+                    .ok()
+                    .filter(|traits| !traits.is_empty())
+                    .ok_or_else(|| {
+                        Error::new_spanned(
+                            attr,
+                            "Typemap attribute `typemap_dyn` expects a list of traits, like `#[typemap_dyn(Debug)]`!",
+                        )
+                    })?;
+                options.dyn_traits.extend(traits);
+            } else if attr.path.is_ident("typemap") {
+                for option in typemap_options(attr)? {
+                    match &option {
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("mut") => mutable = true,
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("readonly") => {
+                            readonly = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("sealed") => {
+                            options.sealed = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("bytes") => {
+                            options.bytes = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("mem_usage") => {
+                            options.mem_usage = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("ffi") => {
+                            options.ffi = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("extract") => {
+                            options.extract = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("reset") => {
+                            options.reset = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("any") => {
+                            options.any = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("fingerprint") => {
+                            options.fingerprint = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("cell") => {
+                            options.cell = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("freeze") => {
+                            options.freeze = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("put") => {
+                            options.put = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("inherent") => {
+                            options.inherent = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("positional") => {
+                            options.positional = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("types") => {
+                            options.types = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("pin") => {
+                            options.pin = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("disjoint") => {
+                            options.disjoint = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("query") => {
+                            options.query = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("visit") => {
+                            options.visit = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("split") => {
+                            options.split = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("view") => {
+                            options.view = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("view_mut") => {
+                            options.view_mut = true
+                        }
+                        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("alias") => {
+                            options.alias = match list.nested.iter().collect::<Vec<_>>().as_slice() {
+                                [NestedMeta::Meta(Meta::Path(path))] if path.get_ident().is_some() => {
+                                    path.get_ident().cloned()
+                                }
+                                _ => return Err(Error::new_spanned(list, "Typemap option `alias` expects a trait name, like `alias(Deps)`!")),
+                            }
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("asref") => {
+                            options.asref = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("borrow") => {
+                            options.borrow = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("into_ref") => {
+                            options.into_ref = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("tuple") => {
+                            options.tuple = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("display") => {
+                            options.display = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("serde") => {
+                            options.serde = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("rhai") => {
+                            options.rhai = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("bevy") => {
+                            options.bevy = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("frunk") => {
+                            options.frunk = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("type_map") => {
+                            options.type_map = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("rayon") => {
+                            options.rayon = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("commands") => {
+                            options.commands = Some(Vec::new())
+                        }
+                        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("commands") => {
+                            options.commands = Some(
+                                list.nested
+                                    .iter()
+                                    .map(|derive| match derive {
+                                        NestedMeta::Meta(Meta::Path(path)) => Ok(path.clone()),
+                                        _ => Err(Error::new_spanned(derive, "Typemap option `commands` expects a list of traits to derive, like `commands(Clone, Debug)`!")),
+                                    })
+                                    .collect::<Result<_>>()?,
+                            )
+                        }
+                        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("into") => {
+                            options.into = list
+                                .nested
+                                .iter()
+                                .map(|target| match target {
+                                    NestedMeta::Meta(Meta::Path(path)) => Ok(path.clone()),
+                                    _ => Err(Error::new_spanned(target, "Typemap option `into` expects a list of typemaps, like `into(Domain)`!")),
+                                })
+                                .collect::<Result<_>>()?
+                        }
+                        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("dyn") => {
+                            for tr in &list.nested {
+                                match tr {
+                                    NestedMeta::Meta(Meta::Path(path)) => options.dyn_traits.push(path.clone()),
+                                    _ => return Err(Error::new_spanned(tr, "Typemap option `dyn` expects a list of traits, like `dyn(Debug)`!")),
+                                }
+                            }
+                        }
+                        NestedMeta::Meta(Meta::NameValue(name_value))
+                            if name_value.path.is_ident("max_size") =>
+                        {
+                            options.max_size = Some(size_option(name_value)?)
+                        }
+                        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("instantiate") => {
+                            options.instantiate = list
+                                .nested
+                                .iter()
+                                .map(|ty| match ty {
+                                    NestedMeta::Lit(Lit::Str(lit)) => lit.parse().map_err(|_| {
+                                        Error::new_spanned(lit, "Typemap option `instantiate` expects types, like `instantiate(\"Map<u8>\")`!")
+                                    }),
+                                    _ => Err(Error::new_spanned(ty, "Typemap option `instantiate` expects strings, like `instantiate(\"Map<u8>\")`!")),
+                                })
+                                .collect::<Result<_>>()?
+                        }
+                        NestedMeta::Meta(Meta::NameValue(name_value))
+                            if name_value.path.is_ident("bound") =>
+                        {
+                            options.bound.extend(match &name_value.lit {
+                                Lit::Str(bound) => bound
+                                    .parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)
+                                    .map_err(|_| {
+                                        Error::new_spanned(bound, "Typemap option `bound` expects where clause predicates, like `bound = \"T: 'static\"`!")
+                                    })?,
+                                lit => return Err(Error::new_spanned(lit, "Typemap option `bound` expects a string, like `bound = \"T: 'static\"`!")),
+                            })
+                        }
+                        NestedMeta::Meta(Meta::NameValue(name_value))
+                            if name_value.path.is_ident("crate") =>
+                        {
+                            options.krate = Some(match &name_value.lit {
+                                Lit::Str(path) => path.parse().map_err(|_| {
+                                    Error::new_spanned(path, "Typemap option `crate` expects a path, like `crate = \"typemap_meta\"`!")
+                                })?,
+                                lit => return Err(Error::new_spanned(lit, "Typemap option `crate` expects a string, like `crate = \"typemap_meta\"`!")),
+                            })
+                        }
+                        _ => return Err(unknown_option(&option, "the struct")),
+                    }
+                }
+            } else if attr.path.is_ident("typemap_volatile") {
+                return Err(Error::new_spanned(
+                    attr,
+                    "Typemap attribute `typemap_volatile` only applies to fields!",
+                ));
+            }
+        }
+        if mutable && readonly {
+            return Err(Error::new_spanned(
+                quote::quote! { #(#attrs)* },
+                "Typemap options `mut` and `readonly` cannot be used together!",
+            ));
+        }
+        options.mutable = mutable;
+        Ok(options)
+    }
+
+    /// Reject the options not supported by enums, which only implement `TryGet` and `TryGetMut`
+    pub fn check_enum_attrs(attrs: &[Attribute]) -> Result<()> {
+        for attr in attrs {
+            if attr.path.is_ident("typemap") {
+                for option in typemap_options(attr)? {
+                    match &option {
+                        NestedMeta::Meta(Meta::Path(path))
+                            if path.is_ident("mut") || path.is_ident("readonly") => {}
+                        NestedMeta::Meta(Meta::NameValue(name_value))
+                            if name_value.path.is_ident("bound")
+                                || name_value.path.is_ident("crate") => {}
+                        NestedMeta::Meta(meta) => {
+                            let name = meta
+                                .path()
+                                .get_ident()
+                                .map_or_else(|| "?".to_string(), |ident| ident.to_string());
+                            return Err(Error::new_spanned(
+                                &option,
+                                format!("Typemap option `{}` does not apply to enums!", name),
+                            ));
+                        }
+                        NestedMeta::Lit(_) => return Err(unknown_option(&option, "the enum")),
+                    }
+                }
+            } else if let Some(ident) = attr.path.get_ident().filter(|ident| {
+                let name = ident.to_string();
+                name.starts_with("typemap_") && name != "typemap_mut" && name != "typemap_readonly"
+            }) {
+                return Err(Error::new_spanned(
+                    attr,
+                    format!("Typemap attribute `{}` does not apply to enums!", ident),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Prefix to the paths of the typemap-meta items in generated code, `::typemap_meta::` if unspecified
+    pub fn crate_prefix(&self) -> proc_macro2::TokenStream {
+        crate_prefix(self.krate.as_ref())
+    }
+}
+
+/// Prefix to the paths of the typemap-meta items in generated code, `::typemap_meta::` if unspecified
+pub(crate) fn crate_prefix(krate: Option<&Path>) -> proc_macro2::TokenStream {
+    match krate {
+        Some(path) => quote::quote! { #path:: },
+        None => quote::quote! { ::typemap_meta:: },
+    }
+}
+
+/// Parse the `crate = "path"` argument of the macros not taking `#[typemap(...)]` attributes
+pub(crate) fn parse_crate(input: ParseStream) -> Result<Path> {
+    input.parse::<Token![crate]>()?;
+    input.parse::<Token![=]>()?;
+    let path = input.parse::<LitStr>()?;
+    path.parse().map_err(|_| {
+        Error::new_spanned(
+            path,
+            "Typemap option `crate` expects a path, like `crate = \"typemap_meta\"`!",
+        )
+    })
+}
+
+impl FieldOptions {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs {
+            if attr.path.is_ident("typemap_volatile") {
+                options.volatile = true;
+            } else if attr.path.is_ident("typemap_skip") {
+                options.skip = true;
+            } else if attr.path.is_ident("typemap_mut") {
+                options.mutable = Some(true);
+            } else if attr.path.is_ident("typemap_readonly") {
+                options.mutable = Some(false);
+            } else if attr.path.is_ident("typemap_flatten") {
+                options.flatten = true;
+            } else if attr.path.is_ident("typemap") {
+                for option in typemap_options(attr)? {
+                    match &option {
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                            options.skip = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("include") => {
+                            options.include = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("mut") => {
+                            options.mutable = Some(true)
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("readonly") => {
+                            options.mutable = Some(false)
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+                            options.flatten = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("volatile") => {
+                            options.volatile = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("boxed") => {
+                            options.boxed = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("pad") => {
+                            options.pad = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("refcell") => {
+                            options.refcell = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("atomic") => {
+                            options.atomic = true
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("embassy") => {
+                            options.embassy = true
+                        }
+                        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("serde") => {
+                            options.serde = SerdeFieldOptions::from_options(&list.nested)?
+                        }
+                        NestedMeta::Meta(Meta::NameValue(name_value))
+                            if name_value.path.is_ident("max_size") =>
+                        {
+                            options.max_size = Some(size_option(name_value)?)
+                        }
+                        NestedMeta::Meta(Meta::NameValue(name_value))
+                            if name_value.path.is_ident("env") =>
+                        {
+                            options.env = Some(match &name_value.lit {
+                                Lit::Str(name) => name.value(),
+                                lit => return Err(Error::new_spanned(lit, "Typemap option `env` expects a string, like `env = \"PORT\"`!")),
+                            })
+                        }
+                        NestedMeta::Meta(Meta::NameValue(name_value))
+                            if name_value.path.is_ident("parse_with") =>
+                        {
+                            options.parse_with = Some(match &name_value.lit {
+                                Lit::Str(path) => path.parse().map_err(|_| {
+                                    Error::new_spanned(path, "Typemap option `parse_with` expects a path to a function!")
+                                })?,
+                                lit => return Err(Error::new_spanned(lit, "Typemap option `parse_with` expects a string!")),
+                            })
+                        }
+                        NestedMeta::Meta(Meta::NameValue(name_value))
+                            if name_value.path.is_ident("default") =>
+                        {
+                            options.default = Some(match &name_value.lit {
+                                Lit::Str(expr) => expr.parse().map_err(|_| {
+                                    Error::new_spanned(expr, "Typemap option `default` expects an expression, like `default = \"42\"`!")
+                                })?,
+                                lit => return Err(Error::new_spanned(lit, "Typemap option `default` expects a string, like `default = \"42\"`!")),
+                            })
+                        }
+                        _ => return Err(unknown_option(&option, "fields")),
+                    }
+                }
+            } else if [
+                "typemap_bytes",
+                "typemap_asref",
+                "typemap_borrow",
+                "typemap_pin",
+                "typemap_alias",
+                "typemap_dyn",
+            ]
+            .iter()
+            .any(|name| attr.path.is_ident(name))
+            {
+                return Err(Error::new_spanned(
+                    attr,
+                    format!(
+                        "Typemap attribute `{}` only applies to the struct!",
+                        attr.path.to_token_stream()
+                    ),
+                ));
+            }
+        }
+        // incompatible options are reported on all the attributes of the field, as they
+        // can come from different ones
+        let error = |message| Err(Error::new_spanned(quote::quote! { #(#attrs)* }, message));
+        if (options.boxed || options.pad || options.atomic) && options.volatile {
+            return error(
+                "Typemap options `boxed`, `pad` and `atomic` cannot be used with `volatile`!",
+            );
+        }
+        if options.flatten && options.volatile {
+            return error("Typemap options `flatten` and `volatile` cannot be used together!");
+        }
+        if options.parse_with.is_some() && options.env.is_none() {
+            return error("Typemap option `parse_with` requires `env`!");
+        }
+        if options.boxed && options.pad {
+            return error("Typemap options `boxed` and `pad` cannot be used together!");
+        }
+        Ok(options)
+    }
+}
+
+impl SerdeFieldOptions {
+    fn from_options<'a>(nested: impl IntoIterator<Item = &'a NestedMeta>) -> Result<Self> {
+        let mut options = Self::default();
+        for option in nested {
+            match option {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => options.skip = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => {
+                    options.default = true
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("rename") =>
+                {
+                    options.rename = Some(match &name_value.lit {
+                        Lit::Str(name) => name.value(),
+                        lit => {
+                            return Err(Error::new_spanned(
+                                lit,
+                                "Typemap serde option `rename` expects a string!",
+                            ))
+                        }
+                    })
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("with") =>
+                {
+                    options.with = Some(match &name_value.lit {
+                        Lit::Str(path) => path.parse().map_err(|_| {
+                            Error::new_spanned(
+                                path,
+                                "Typemap serde option `with` expects a path to a module!",
+                            )
+                        })?,
+                        lit => {
+                            return Err(Error::new_spanned(
+                                lit,
+                                "Typemap serde option `with` expects a string!",
+                            ))
+                        }
+                    })
+                }
+                _ => return Err(unknown_option(option, "serde")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn typemap_options(attr: &Attribute) -> Result<impl Iterator<Item = NestedMeta>> {
+    match attr.parse_meta() {
+        Ok(Meta::List(list)) => Ok(list.nested.into_iter()),
+        _ => Err(Error::new_spanned(
+            attr,
+            "Typemap attribute must be of the form #[typemap(option, ...)]!",
+        )),
+    }
+}
+
+fn size_option(name_value: &MetaNameValue) -> Result<LitInt> {
+    match &name_value.lit {
+        Lit::Int(size) => Ok(size.clone()),
+        lit => Err(Error::new_spanned(
+            lit,
+            "Typemap option `max_size` expects a number of bytes, like `max_size = 64`!",
+        )),
+    }
+}
+
+fn unknown_option(option: &NestedMeta, target: &str) -> Error {
+    let name = match option {
+        NestedMeta::Meta(meta) => meta
+            .path()
+            .get_ident()
+            .map_or_else(|| "?".to_string(), |ident| ident.to_string()),
+        NestedMeta::Lit(_) => "?".to_string(),
+    };
+    Error::new_spanned(
+        option,
+        format!("Unknown typemap option `{}` for {}!", name, target),
+    )
+}
@@ -0,0 +1,105 @@
+//! The `borrow_map!` macro, building an anonymous typemap of references.
+
+use crate::attr;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Path, Token, Type};
+
+/// A reference to a value, along with the type of the value
+struct Borrow {
+    mutable: bool,
+    expr: TokenStream,
+    ty: Type,
+}
+
+impl Parse for Borrow {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![&]>()?;
+        let mutable = input.parse::<Option<Token![mut]>>()?.is_some();
+        // the referenced expression extends up to the colon introducing its type
+        let mut expr = TokenStream::new();
+        let at_colon = |input: ParseStream| input.peek(Token![:]) && !input.peek(Token![::]);
+        while !input.is_empty() && !at_colon(input) {
+            if input.peek(Token![::]) {
+                input.parse::<Token![::]>()?.to_tokens(&mut expr);
+            } else {
+                expr.extend([input.parse::<proc_macro2::TokenTree>()?]);
+            }
+        }
+        if expr.is_empty() {
+            return Err(input.error("expected a borrowed expression, like `&value: Type`"));
+        }
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        Ok(Self { mutable, expr, ty })
+    }
+}
+
+pub(crate) struct BorrowMap {
+    krate: Option<Path>,
+    borrows: Vec<Borrow>,
+}
+
+impl Parse for BorrowMap {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let krate = if input.peek(Token![crate]) {
+            let krate = attr::parse_crate(input)?;
+            input.parse::<Token![,]>()?;
+            Some(krate)
+        } else {
+            None
+        };
+        let borrows = input.parse_terminated::<_, Token![,]>(Borrow::parse)?;
+        Ok(Self {
+            krate,
+            borrows: borrows.into_iter().collect(),
+        })
+    }
+}
+
+impl BorrowMap {
+    pub fn expand(&self) -> syn::Result<TokenStream> {
+        let krate = attr::crate_prefix(self.krate.as_ref());
+        let name = format_ident!("BorrowMap");
+        let field_types = self.borrows.iter().map(|borrow| {
+            let ty = &borrow.ty;
+            if borrow.mutable {
+                quote! { &'a mut #ty }
+            } else {
+                quote! { &'a #ty }
+            }
+        });
+        let types: Vec<_> = self.borrows.iter().map(|borrow| &borrow.ty).collect();
+        let indices: Vec<_> = (0..self.borrows.len()).map(syn::Index::from).collect();
+        let (mut_types, mut_indices): (Vec<_>, Vec<_>) = self
+            .borrows
+            .iter()
+            .zip(&indices)
+            .filter(|(borrow, _)| borrow.mutable)
+            .map(|(borrow, index)| (&borrow.ty, index))
+            .unzip();
+        let values = self.borrows.iter().map(|borrow| {
+            let expr = &borrow.expr;
+            if borrow.mutable {
+                quote! { &mut #expr }
+            } else {
+                quote! { &#expr }
+            }
+        });
+        Ok(quote! {{
+            struct #name<'a>(#(#field_types),*);
+            #(impl #krate Get<#types> for #name<'_> {
+                fn get(&self) -> &#types {
+                    &*self.#indices
+                }
+            })*
+            #(impl #krate GetMut<#mut_types> for #name<'_> {
+                fn get_mut(&mut self) -> &mut #mut_types {
+                    &mut *self.#mut_indices
+                }
+            })*
+            #name(#(#values),*)
+        }})
+    }
+}
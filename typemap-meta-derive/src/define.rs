@@ -0,0 +1,101 @@
+//! The `typemap!` macro, defining a typemap from the list of its types.
+
+use crate::attr::ContainerOptions;
+use crate::ty;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{braced, Attribute, Ident, Token, Type, Visibility};
+
+pub(crate) struct Definition {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    types: Punctuated<Type, Token![,]>,
+    constructor: Option<(Visibility, Ident)>,
+}
+
+impl Parse for Definition {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![struct]>()?;
+        let name = input.parse()?;
+        let content;
+        braced!(content in input);
+        let types = content.parse_terminated(Type::parse)?;
+        let constructor = if input.is_empty() {
+            None
+        } else {
+            let vis = input.parse()?;
+            input.parse::<Token![fn]>()?;
+            let name = input.parse()?;
+            input.parse::<Token![;]>()?;
+            Some((vis, name))
+        };
+        Ok(Self {
+            attrs,
+            vis,
+            name,
+            types,
+            constructor,
+        })
+    }
+}
+
+impl Definition {
+    pub fn expand(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let krate = ContainerOptions::from_attrs(&self.attrs)?.crate_prefix();
+        let attrs = &self.attrs;
+        let vis = &self.vis;
+        let name = &self.name;
+        let types: Vec<_> = self.types.iter().collect();
+        let gen_constructor = self.constructor.as_ref().map(|(fn_vis, fn_name)| {
+            let args = parameter_names(&types);
+            quote! {
+                impl #name {
+                    #fn_vis fn #fn_name(#(#args: #types),*) -> Self {
+                        Self(#(#args),*)
+                    }
+                }
+            }
+        });
+        Ok(quote! {
+            #[derive(#krate Typemap)]
+            #(#attrs)*
+            #vis struct #name(#(#vis #types),*);
+            #gen_constructor
+        })
+    }
+}
+
+/// Names of the constructor parameters, the snake case names of the types if they are distinct
+fn parameter_names(types: &[&Type]) -> Vec<Ident> {
+    let names: Vec<_> = types
+        .iter()
+        .map(|ty| match ty {
+            Type::Path(path) if path.qself.is_none() => path
+                .path
+                .segments
+                .last()
+                .map(|segment| ty::snake_case(&segment.ident.to_string())),
+            _ => None,
+        })
+        .collect();
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| match name {
+            Some(name)
+                if names
+                    .iter()
+                    .filter(|other| other.as_ref() == Some(name))
+                    .count()
+                    == 1 =>
+            {
+                syn::parse_str(name).unwrap_or_else(|_| format_ident!("value{}", i))
+            }
+            _ => format_ident!("value{}", i),
+        })
+        .collect()
+}
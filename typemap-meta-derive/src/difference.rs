@@ -0,0 +1,88 @@
+//! The `difference!` macro, generating a typemap with the types of one typemap absent in another.
+
+use crate::attr::ContainerOptions;
+use crate::migrate::{same_type, TupleStruct};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, Ident, Token, Visibility};
+
+pub(crate) struct Difference {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    minuend: TupleStruct,
+    subtrahend: TupleStruct,
+}
+
+impl Parse for Difference {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![struct]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let minuend = input.parse()?;
+        input.parse::<Token![-]>()?;
+        let subtrahend = input.parse()?;
+        if input.peek(Token![;]) {
+            input.parse::<Token![;]>()?;
+        }
+        Ok(Self {
+            attrs,
+            vis,
+            name,
+            minuend,
+            subtrahend,
+        })
+    }
+}
+
+impl Difference {
+    pub fn expand(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let source_fields: Vec<_> = (0..self.minuend.types.len())
+            .map(|i| format_ident!("source_{}", i))
+            .collect();
+        let (kept_types, kept_fields): (Vec<_>, Vec<_>) = self
+            .minuend
+            .types
+            .iter()
+            .zip(&source_fields)
+            .filter(|(ty, _)| !self.subtrahend.types.iter().any(|t| same_type(t, ty)))
+            .unzip();
+
+        let krate = ContainerOptions::from_attrs(&self.attrs)?.crate_prefix();
+        let attrs = &self.attrs;
+        let vis = &self.vis;
+        let name = &self.name;
+        let minuend_path = &self.minuend.path;
+        let minuend_types = &self.minuend.types;
+        let subtrahend_path = &self.subtrahend.path;
+        let subtrahend_types = &self.subtrahend.types;
+        let subtrahend_fields: Vec<_> = (0..subtrahend_types.len())
+            .map(|i| format_ident!("field_{}", i))
+            .collect();
+        Ok(quote! {
+            #[derive(#krate Typemap)]
+            #(#attrs)*
+            #vis struct #name(#(#vis #kept_types),*);
+
+            impl ::core::convert::From<#minuend_path> for #name {
+                #[allow(unused_variables)]
+                fn from(source: #minuend_path) -> Self {
+                    let #minuend_path(#(#source_fields),*) = source;
+                    #(let #source_fields: #minuend_types = #source_fields;)*
+                    #name(#(#kept_fields),*)
+                }
+            }
+
+            // the subtrahend must be declared with the types of its fields, like the minuend
+            const _: () = {
+                #[allow(dead_code)]
+                fn check_subtrahend(subtrahend: #subtrahend_path) {
+                    let #subtrahend_path(#(#subtrahend_fields),*) = subtrahend;
+                    #(let _: #subtrahend_types = #subtrahend_fields;)*
+                }
+            };
+        })
+    }
+}
@@ -0,0 +1,98 @@
+//! The `#[typemap_fields(...)]` attribute, also available as `#[typemap(fields(...))]`,
+//! exposing selected fields of a struct as a typemap.
+
+use crate::{attr, ty};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parenthesized, token, Data, DeriveInput, Fields, Ident, Path, Token};
+
+mod kw {
+    syn::custom_keyword!(fields);
+}
+
+/// The names of the fields to expose, given directly or within `fields(...)`, and the path to the
+/// crate given by `crate = "path"`
+pub(crate) struct FieldNames {
+    krate: Option<Path>,
+    names: Vec<Ident>,
+}
+
+impl Parse for FieldNames {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut krate = None;
+        let mut names = vec![];
+        while !input.is_empty() {
+            if input.peek(Token![crate]) {
+                krate = Some(attr::parse_crate(input)?);
+            } else if input.peek(kw::fields) && input.peek2(token::Paren) {
+                input.parse::<kw::fields>()?;
+                let content;
+                parenthesized!(content in input);
+                let fields = content.parse_terminated::<_, Token![,]>(Ident::parse)?;
+                names.extend(fields);
+            } else {
+                names.push(input.parse()?);
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(Self { krate, names })
+    }
+}
+
+impl FieldNames {
+    pub fn expand(&self, item: &DeriveInput) -> syn::Result<TokenStream> {
+        let fields =
+            match &item.data {
+                Data::Struct(data) => match &data.fields {
+                    Fields::Named(fields) => &fields.named,
+                    _ => return Err(syn::Error::new_spanned(
+                        &item.ident,
+                        "Typemap attribute `typemap_fields` expects a struct with named fields!",
+                    )),
+                },
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        &item.ident,
+                        "Typemap attribute `typemap_fields` expects a struct with named fields!",
+                    ))
+                }
+            };
+        let mut types: Vec<&syn::Type> = Vec::new();
+        for name in &self.names {
+            let field = fields
+                .iter()
+                .find(|f| f.ident.as_ref() == Some(name))
+                .ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        name,
+                        format!("Typemap field `{}` does not exist in {}!", name, item.ident),
+                    )
+                })?;
+            if types
+                .iter()
+                .any(|ty| ty::type_name(ty) == ty::type_name(&field.ty))
+            {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "Typemap fields selected by `typemap_fields` must have distinct types!",
+                ));
+            }
+            types.push(&field.ty);
+        }
+        let krate = attr::crate_prefix(self.krate.as_ref());
+        let names = &self.names;
+        let name = &item.ident;
+        let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+        Ok(quote! {
+            #item
+            #(impl #impl_generics #krate Get<#types> for #name #ty_generics #where_clause {
+                fn get(&self) -> &#types {
+                    &self.#names
+                }
+            })*
+        })
+    }
+}
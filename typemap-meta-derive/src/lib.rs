@@ -1,67 +1,2646 @@
 extern crate proc_macro;
 
+mod attr;
+mod borrow_map;
+mod define;
+mod difference;
+mod fields;
+mod migrate;
+mod rhai;
+mod serde;
+mod ty;
+
+use attr::{ContainerOptions, FieldOptions};
 use proc_macro::TokenStream;
-use quote::{quote, ToTokens};
-use syn::{self, Attribute, Data, Fields};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{self, parse_quote, spanned::Spanned, Data, Fields};
 
-/// Add static type-to-value getters to a tuple struct containing disjoint heterogeneous types
-#[proc_macro_derive(Typemap, attributes(typemap_mut))]
+/// Add static type-to-value getters to a struct containing disjoint heterogeneous types
+///
+/// On an enum whose variants hold disjoint types, implement `TryGet`, and with `mut`
+/// `TryGetMut`, for these types, returning the value of the active variant. Enums only accept
+/// the `mut`, `readonly`, `bound` and `crate` options.
+///
+/// The struct accepts the `#[typemap(...)]` attribute with the following options:
+/// - `mut`: also implement `GetMut`, equivalent to `#[typemap_mut]`
+/// - `readonly`: do not implement `GetMut`, which is the default, equivalent to
+///   `#[typemap_readonly]`
+/// - `sealed`: prevent other crates from implementing `Get`/`GetMut` on the struct
+/// - `bytes`: implement `AsBytes`, equivalent to `#[typemap_bytes]`, skipped fields being left
+///   out of the encoding and decoded with their default value
+/// - `mem_usage`: implement `MemUsage` by summing the usage of the fields
+/// - `ffi`: require `#[repr(C)]` and fields implementing `FfiSafe`, implement `FfiSafe` and
+///   provide the layout of the fields as the `LAYOUT` constant
+/// - `extract`: generate `extracted_from`, building the struct by cloning its fields from any
+///   typemap containing their types
+/// - `reset`: generate `clear_all`, setting all `Option` fields to `None`, and `reset`,
+///   setting all fields to their declared or default value
+/// - `any`: implement `AnyTypemap` and `DynTypemap`, looking up fields by type at run time,
+///   and generate `iter_any`, `fold_fields`, `get_any` and, if mutable, `iter_any_mut` and
+///   `get_any_mut`, requires all fields to be `'static`
+/// - `fingerprint`: implement `Fingerprint` by hashing the type names of the fields
+/// - `cell`: generate a `{Name}Cell` companion struct wrapping each field in a `Cell`, or a
+///   `RefCell` with the `refcell` field option, implementing `Get` for the wrapped types,
+///   `CellGet` for the types of the values, with `get`, `set` and `replace` methods taking them
+///   as a generic parameter, and conversions from and to the struct
+/// - `freeze`: generate `freeze`, turning the struct into a cheaply cloneable `Frozen` one
+///   implementing `Get`, requires the `alloc` feature
+/// - `put`: generate `put`, assigning a value to the field of its type and returning the
+///   struct, allowing to chain assignments
+/// - `positional`: implement `GetAt` for the fields accessible by reference, with their
+///   position in the declaration of the struct
+/// - `types`: implement the `Typemap` umbrella trait, whose `Types` associated type is the
+///   tuple of the types of all fields, in declaration order, and which provides their number
+///   and the names of their types
+/// - `pin`: implement `GetPinMut` for the mutable fields, which are structurally pinned,
+///   equivalent to `#[typemap_pin]`; the struct is then only `Unpin` if these fields are, and
+///   must not implement `Drop`
+/// - `disjoint`: implement `GetDisjointMut` for all the pairs of distinct mutable fields
+/// - `query`: implement `QueryField` for the fields accessible by reference, and
+///   `QueryFieldMut` for the mutable ones, allowing to borrow several of them at once with
+///   `query!`
+/// - `visit`: generate `accept`, calling a `TypemapVisitor` on all the fields accessible by
+///   reference, in declaration order
+/// - `dyn(Trait, ...)`: generate `for_each_trait` for each of the given traits, named after the
+///   trait in snake case, calling a closure on all the fields accessible by reference as
+///   `&dyn Trait`, in declaration order, and available if their types implement the trait,
+///   equivalent to `#[typemap_dyn(Trait, ...)]`, as well as `map_trait`, returning the results of
+///   the closure paired with the names of the field types, for instance to aggregate the results
+///   of a fallible trait method into a per-type report
+/// - `rayon`: also generate `par_for_each_trait` and, if mutable, `par_for_each_trait_mut` for
+///   the traits given with `dyn`, calling a closure on all the fields, respectively the mutable
+///   ones, in parallel on the rayon thread pool, requires the `rayon` feature
+/// - `split`: generate `split_mut`, returning a tuple of mutable references to all the mutable
+///   fields accessible by reference, in declaration order
+/// - `view`: generate a `{Name}Ref` companion struct holding references to the fields
+///   accessible by reference, cheaply copyable and implementing `Get` for their types, and
+///   `as_view` borrowing the struct as such
+/// - `view_mut`: generate a `{Name}Mut` companion struct holding mutable references to the
+///   mutable fields and shared references to the others, implementing `Get` and `GetMut`
+///   accordingly, and `as_view_mut` mutably borrowing the struct as such
+/// - `alias(Name)`: generate the `Name` trait, with the visibility of the struct, requiring
+///   `Get` for the types of the fields accessible by reference and implemented by all the
+///   types implementing these, equivalent to `#[typemap_alias(Name)]`
+/// - `inherent`: generate the `get` and, if mutable, `get_mut` methods, generic over the type
+///   of the field, allowing calls like `map.get::<T>()`
+/// - `asref`: also implement `AsRef` for the field types, and `AsMut` for the mutable ones,
+///   equivalent to `#[typemap_asref]`
+/// - `borrow`: also implement `Borrow` for the field types, and `BorrowMut` for the mutable ones,
+///   equivalent to `#[typemap_borrow]`
+/// - `into_ref`: implement `From<&Name> for &T` for the field types `T`, and
+///   `From<&mut Name> for &mut T` for the mutable ones, except for fields whose type is a
+///   generic parameter of the struct
+/// - `tuple`: implement the conversions from and to the tuple of the types of all the fields,
+///   in declaration order
+/// - `into(Target, ...)`: implement `From<Name>` for the given typemaps, deriving `Typemap`,
+///   by converting into each of their fields the only field whose type converts into its type
+///   with `From`, a different one for every target field
+/// - `display`: implement `Display` as a single line listing the type names of the fields,
+///   followed by their values for those implementing `Display`
+/// - `commands` or `commands(Derive, ...)`: implement `Commands`, generating a `{Name}Command`
+///   enumeration of the field mutations, with the given derives
+/// - `serde`: implement serde's `Serialize` and `Deserialize`, using type names as field names,
+///   requires the `serde` feature
+/// - `rhai`: generate `register_rhai`, allowing rhai scripts to access the fields by type name,
+///   requires the `rhai` feature
+/// - `bevy`: generate `insert_into_world` and `extract_from_world`, moving the fields in and out
+///   of a Bevy `World` as resources, requires the `bevy` feature
+/// - `frunk`: implement frunk's `Generic` with the HList of all fields as representation,
+///   generate `into_hlist` and `from_hlist`, and implement frunk's `Selector` for the mutable
+///   fields, requires the `frunk` feature
+/// - `type_map`: generate `into_type_map`, inserting every field by its type into a runtime
+///   `type_map::TypeMap`, requires the `type-map` feature
+/// - `max_size = N`: fail compilation if the struct is larger than `N` bytes
+/// - `instantiate("Type", ...)`: concrete instantiations of a generic struct, for which
+///   `max_size` is checked
+/// - `bound = "predicates"`: additional where clause predicates of the generated items,
+///   like `bound = "T: 'static"`
+/// - `crate = "path"`: path to the `typemap_meta` crate, if re-exported from another one,
+///   `::typemap_meta` by default
+///
+/// The fields accept the `#[typemap(...)]` attribute with the following options:
+/// - `skip`: do not implement accessors for this field, equivalent to `#[typemap_skip]`,
+///   the default for `PhantomData` fields
+/// - `include`: implement accessors for a `PhantomData` field
+/// - `mut`: implement mutable accessors for this field, even if the struct is not `mut`,
+///   equivalent to `#[typemap_mut]`
+/// - `readonly`: do not implement mutable accessors for this field, even if the struct is `mut`,
+///   equivalent to `#[typemap_readonly]`
+/// - `volatile`: only access this field by value using volatile operations,
+///   equivalent to `#[typemap_volatile]`
+/// - `flatten`: the field is itself a typemap, implement `Get` (and `GetMut` if mutable) for
+///   all the types it provides by forwarding to it, instead of for the type of the field,
+///   equivalent to `#[typemap_flatten]`; at most one field can be flattened, and it cannot
+///   provide the types of the other fields
+/// - `boxed`: the field is declared as `Box<T>` but its accessors are keyed on `T`
+/// - `pad`: the field is declared as `CachePadded<T>` but its accessors are keyed on `T`
+/// - `refcell`: wrap the field in a `RefCell` instead of a `Cell` in the `cell` companion struct
+/// - `atomic`: the field is an atomic type of `core::sync::atomic`, implement `AtomicGet`
+///   for the type of its value
+/// - `embassy`: the field is a `Mutex<M, T>` of `embassy-sync` written without its
+///   `embassy_sync` path, implement `MutexGet` for it, requires the `embassy` feature
+/// - `serde(...)`: customize the serialization of this field with `rename = "name"`,
+///   `with = "module"` (providing `serialize` and `deserialize` functions), `skip` and
+///   `default` (using the default value if the field is missing when deserializing, which is
+///   always the case for `Option` fields not using `with`)
+/// - `max_size = N`: fail compilation if the field is larger than `N` bytes
+/// - `env = "NAME"`: read the field from the environment variable `NAME` in the generated
+///   `from_env` constructor, or from the variables given by a closure to `from_env_with`,
+///   parsing it with `FromStr` or the function given by `parse_with = "path"`, requires the
+///   `env` feature
+/// - `default = "expr"`: value given to the field by `reset`, instead of its `Default` one
+#[proc_macro_derive(
+    Typemap,
+    attributes(
+        typemap,
+        typemap_mut,
+        typemap_readonly,
+        typemap_volatile,
+        typemap_bytes,
+        typemap_asref,
+        typemap_borrow,
+        typemap_pin,
+        typemap_alias,
+        typemap_dyn,
+        typemap_skip,
+        typemap_flatten
+    )
+)]
 pub fn typemap_macro_derive(input: TokenStream) -> TokenStream {
     // Construct a representation of Rust code as a syntax tree
     // that we can manipulate
-    let ast = syn::parse(input).unwrap();
+    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
 
     // Build the trait implementation
     impl_typemap_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-fn impl_typemap_macro(ast: &syn::DeriveInput) -> TokenStream {
-    let struct_data = if let Data::Struct(s) = &ast.data {
-        s
-    } else {
-        panic!("Typemap only applies to tuple struct, but used on a non-struct!")
-    };
-    let tuple_fields = if let Fields::Unnamed(f) = &struct_data.fields {
-        f
-    } else {
-        panic!("Typemap only applies to tuple struct, but used on a non-tuple struct!")
+/// Generate a conversion between two versions of a typemap
+///
+/// The fields of both versions are listed, and the conversion moves the fields of types present
+/// in both versions, converts the fields listed with `convert`, using [`From`] or the given
+/// function, and fills the fields of new types with their [`Default`] value.
+/// Types of the source version absent in the target one must be explicitly converted or dropped.
+/// ```text
+/// migrate! {
+///     MapV1(A, B, OldC, Removed) => MapV2(A, B, C, New);
+///     convert OldC => C with convert_c;
+///     drop Removed;
+/// }
+/// ```
+#[proc_macro]
+pub fn migrate(input: TokenStream) -> TokenStream {
+    let migration = syn::parse_macro_input!(input as migrate::Migration);
+    migration
+        .expand()
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Define a typemap from the list of its types
+///
+/// The generated tuple struct derives `Typemap`, accepts attributes such as `#[typemap(mut)]`,
+/// including `#[typemap(crate = "path")]` to set the path of the derive, and its fields have the
+/// visibility of the struct. A constructor taking the values in order
+/// is generated if its signature follows the struct.
+/// ```text
+/// typemap! {
+///     #[typemap(mut)]
+///     pub struct AppCtx { Config, Db, Cache }
+///     pub fn new;
+/// }
+/// ```
+#[proc_macro]
+pub fn typemap(input: TokenStream) -> TokenStream {
+    let definition = syn::parse_macro_input!(input as define::Definition);
+    definition
+        .expand()
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generate a typemap containing the types of a typemap that are absent in another one
+///
+/// The generated struct derives `Typemap`, accepts attributes such as `#[typemap(mut)]`,
+/// including `#[typemap(crate = "path")]` to set the path of the derive, and can be extracted from the first typemap using [`From`], dropping the other fields.
+/// Both typemaps must be tuple structs declared with the types of their fields, in order, which
+/// is checked by the generated code.
+/// ```text
+/// difference! {
+///     #[typemap(mut)]
+///     pub struct Local = Context(Logger, Config, Db) - Shared(Config, Metrics);
+/// }
+/// ```
+#[proc_macro]
+pub fn difference(input: TokenStream) -> TokenStream {
+    let difference = syn::parse_macro_input!(input as difference::Difference);
+    difference
+        .expand()
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Build an anonymous typemap of references to values
+///
+/// Each value is given as a shared or mutable reference, followed by its type, and the typemap
+/// implements `Get` for all of them, and `GetMut` for the mutable ones.
+/// The types must not contain elided lifetimes. The path to the `typemap_meta` crate can be set
+/// with a leading `crate = "path"` argument.
+/// ```text
+/// let ctx = borrow_map!(&config: Config, &mut world: World, &assets: Assets);
+/// ```
+#[proc_macro]
+pub fn borrow_map(input: TokenStream) -> TokenStream {
+    let borrow_map = syn::parse_macro_input!(input as borrow_map::BorrowMap);
+    borrow_map
+        .expand()
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Expose selected fields of a struct with named fields as a typemap, implementing `Get` for their types
+///
+/// The struct is otherwise left untouched, and the selected fields must have distinct types.
+/// The path to the `typemap_meta` crate can be set with a `crate = "path"` argument.
+/// ```text
+/// #[typemap_fields(db, cache)]
+/// struct App { db: Database, cache: Cache, name: String }
+/// ```
+#[proc_macro_attribute]
+pub fn typemap_fields(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let names = syn::parse_macro_input!(attr as fields::FieldNames);
+    let item = syn::parse_macro_input!(item as syn::DeriveInput);
+    names
+        .expand(&item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// A field of the typemap, along with its options
+struct Field<'a> {
+    index: syn::Index,
+    /// The name of the field, or its index in tuple structs
+    member: syn::Member,
+    ty: &'a syn::Type,
+    options: FieldOptions,
+    /// Whether mutable accessors are generated, from the field or the struct options
+    mutable: bool,
+}
+
+impl Field<'_> {
+    /// The type through which the field is accessed, which differs from the declared one for wrapped fields
+    fn key_ty(&self) -> &syn::Type {
+        // the wrapper types are checked when parsing the field options
+        if self.options.boxed {
+            ty::wrapped_type(self.ty, "Box").unwrap_or(self.ty)
+        } else if self.options.pad {
+            ty::wrapped_type(self.ty, "CachePadded").unwrap_or(self.ty)
+        } else {
+            self.ty
+        }
+    }
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::from_attrs(&field.attrs)?;
+    if options.boxed && ty::wrapped_type(&field.ty, "Box").is_none() {
+        return Err(syn::Error::new_spanned(
+            &field.ty,
+            "Typemap option `boxed` requires a field of type `Box<T>`!",
+        ));
+    }
+    if options.pad && ty::wrapped_type(&field.ty, "CachePadded").is_none() {
+        return Err(syn::Error::new_spanned(
+            &field.ty,
+            "Typemap option `pad` requires a field of type `CachePadded<T>`!",
+        ));
+    }
+    // markers carry no value, so they are not exposed unless explicitly included
+    if !options.include && ty::wrapped_type(&field.ty, "PhantomData").is_some() {
+        options.skip = true;
+    }
+    Ok(options)
+}
+
+fn impl_typemap_macro(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let options = ContainerOptions::from_attrs(&input.attrs)?;
+    // the custom bounds apply to all the generated items, as if they were declared on the type
+    let mut ast = input.clone();
+    if !options.bound.is_empty() {
+        let where_clause = ast.generics.make_where_clause();
+        where_clause
+            .predicates
+            .extend(options.bound.iter().cloned());
+    }
+    let ast = &ast;
+    let struct_data = match &ast.data {
+        Data::Struct(s) => s,
+        Data::Enum(e) => {
+            ContainerOptions::check_enum_attrs(&ast.attrs)?;
+            return impl_enum(ast, &options, e);
+        }
+        Data::Union(u) => {
+            return Err(syn::Error::new_spanned(
+                u.union_token,
+                "Typemap only applies to struct or enum, but used on a union!",
+            ))
+        }
     };
-    let all_mut = has_mut_attr(&ast.attrs);
+    if let Fields::Unit = &struct_data.fields {
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "Typemap only applies to struct with fields, but used on a unit struct!",
+        ));
+    }
+    let fields = struct_data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let field_options = field_options(f)?;
+            Ok(Field {
+                index: syn::Index::from(i),
+                member: match &f.ident {
+                    Some(ident) => syn::Member::Named(ident.clone()),
+                    None => syn::Member::Unnamed(syn::Index::from(i)),
+                },
+                ty: &f.ty,
+                mutable: field_options.mutable.unwrap_or(options.mutable),
+                options: field_options,
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let krate = options.crate_prefix();
+
+    // volatile fields are only accessible by value, so they are kept apart from the others
+    let (volatile_fields, ref_fields): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .filter(|f| !f.options.skip && !f.options.flatten)
+        .partition(|f| f.options.volatile);
+    let flatten_fields: Vec<_> = fields
+        .iter()
+        .filter(|f| !f.options.skip && f.options.flatten)
+        .collect();
 
-    let types: Vec<_> = tuple_fields
-        .unnamed
+    // volatile fields are accessed through other traits, so they only need distinct types
+    // among themselves
+    check_duplicate_types(&ref_fields)?;
+    check_duplicate_types(&volatile_fields)?;
+    check_generic_overlaps(ast, &field_keys(&ref_fields))?;
+    check_generic_overlaps(ast, &field_keys(&volatile_fields))?;
+
+    // accessors rely on deref coercion to reach the key type of wrapped fields
+    let types: Vec<_> = ref_fields
+        .iter()
+        .map(|f| f.key_ty().to_token_stream())
+        .collect();
+    let indices: Vec<_> = ref_fields.iter().map(|f| &f.member).collect();
+    let volatile_types: Vec<_> = volatile_fields.iter().map(|f| f.ty).collect();
+    let volatile_indices: Vec<_> = volatile_fields.iter().map(|f| &f.member).collect();
+    let mut_fields: Vec<_> = ref_fields.iter().filter(|f| f.mutable).collect();
+    let mut_types: Vec<_> = mut_fields
         .iter()
-        .map(|e| e.ty.to_token_stream())
+        .map(|f| f.key_ty().to_token_stream())
         .collect();
-    let indices: Vec<_> = (0..types.len()).map(syn::Index::from).collect();
+    let mut_indices: Vec<_> = mut_fields.iter().map(|f| &f.member).collect();
+    let mut_volatile_fields: Vec<_> = volatile_fields.iter().filter(|f| f.mutable).collect();
+    let mut_volatile_types: Vec<_> = mut_volatile_fields.iter().map(|f| f.ty).collect();
+    let mut_volatile_indices: Vec<_> = mut_volatile_fields.iter().map(|f| &f.member).collect();
     let name = &ast.ident;
     let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let gen_get = if options.sealed {
+        impl_sealed(ast, &options, &types, &indices, &mut_types, &mut_indices)
+    } else {
+        quote! {
+            #(impl #impl_generics #krate Get<#types> for #name #ty_generics #where_clause {
+                fn get(&self) -> &#types {
+                    &self.#indices
+                }
+            })*
+        }
+    };
     let gen = quote! {
-        #(impl #generics Get<#types> for #name #generics {
-            fn get(&self) -> &#types {
-                &self.#indices
+        #gen_get
+        #(impl #impl_generics #krate ReadVolatile<#volatile_types> for #name #ty_generics #where_clause {
+            fn read_volatile(&self) -> #volatile_types {
+                #krate __private::read_volatile(&self.#volatile_indices)
+            }
+        })*
+    };
+    let gen_get_mut = if options.sealed {
+        None
+    } else {
+        Some(quote! {
+            #(impl #impl_generics #krate GetMut<#mut_types> for #name #ty_generics #where_clause {
+                fn get_mut(&mut self) -> &mut #mut_types {
+                    &mut self.#mut_indices
+                }
+            })*
+        })
+    };
+    let gen_flatten = match flatten_fields.as_slice() {
+        [] => None,
+        [field] => Some(impl_flatten(ast, &options, field)?),
+        [_, field, ..] => {
+            return Err(syn::Error::new_spanned(
+                field.ty,
+                "Typemap option `flatten` can only be used on a single field!",
+            ))
+        }
+    };
+    let gen_mut = quote! {
+        #gen_get_mut
+        #(impl #impl_generics #krate WriteVolatile<#mut_volatile_types> for #name #ty_generics #where_clause {
+            fn write_volatile(&mut self, value: #mut_volatile_types) {
+                #krate __private::write_volatile(&mut self.#mut_volatile_indices, value)
+            }
+        })*
+    };
+
+    // optional fields can be probed for their inner type, unless another field has this type,
+    // in which case TryGet is already implemented through Get
+    let option_fields: Vec<_> = ref_fields
+        .iter()
+        .filter_map(|f| ty::wrapped_type(f.ty, "Option").map(|ty| (f, ty)))
+        .filter(|(_, ty)| {
+            !ref_fields
+                .iter()
+                .any(|f| ty::type_name(f.key_ty()) == ty::type_name(ty))
+        })
+        .collect();
+    // TryGet is implemented for these types along with the types accessible by reference
+    let try_get_keys: Vec<_> = field_keys(&ref_fields)
+        .into_iter()
+        .chain(option_fields.iter().map(|(f, ty)| (**f, *ty)))
+        .collect();
+    check_generic_overlaps(ast, &try_get_keys)?;
+    let option_types: Vec<_> = option_fields.iter().map(|(_, ty)| ty).collect();
+    let option_indices: Vec<_> = option_fields.iter().map(|(f, _)| &f.member).collect();
+    let (mut_option_types, mut_option_indices): (Vec<&syn::Type>, Vec<_>) = option_fields
+        .iter()
+        .filter(|(f, _)| f.mutable)
+        .map(|(f, ty)| (*ty, &f.member))
+        .unzip();
+    let gen_try_get = quote! {
+        #(impl #impl_generics #krate TryGet<#option_types> for #name #ty_generics #where_clause {
+            fn try_get(&self) -> Option<&#option_types> {
+                self.#option_indices.as_ref()
+            }
+        })*
+    };
+    let gen_try_get_mut = quote! {
+        #(impl #impl_generics #krate TryGetMut<#mut_option_types> for #name #ty_generics #where_clause {
+            fn try_get_mut(&mut self) -> Option<&mut #mut_option_types> {
+                self.#mut_option_indices.as_mut()
             }
         })*
     };
-    let gen_mut = if all_mut {
+
+    // collections can be accessed by their element type, bounded ones possibly being full
+    let collection_fields: Vec<_> = ref_fields
+        .iter()
+        .filter_map(|f| ty::vec_element(f.ty).map(|(ty, bounded)| (f, ty, bounded)))
+        .collect();
+    check_generic_overlaps(
+        ast,
+        &collection_fields
+            .iter()
+            .map(|(f, ty, _)| (**f, *ty))
+            .collect::<Vec<_>>(),
+    )?;
+    let element_types: Vec<_> = collection_fields.iter().map(|(_, ty, _)| ty).collect();
+    let collection_indices: Vec<_> = collection_fields
+        .iter()
+        .map(|(f, _, _)| &f.member)
+        .collect();
+    let mut_collection_fields: Vec<_> = collection_fields
+        .iter()
+        .filter(|(f, _, _)| f.mutable)
+        .collect();
+    let mut_element_types: Vec<_> = mut_collection_fields.iter().map(|(_, ty, _)| ty).collect();
+    let mut_collection_indices: Vec<_> = mut_collection_fields
+        .iter()
+        .map(|(f, _, _)| &f.member)
+        .collect();
+    let pushes = mut_collection_fields.iter().map(|(f, _, bounded)| {
+        let index = &f.member;
+        if *bounded {
+            quote! { self.#index.push(value) }
+        } else {
+            quote! {
+                self.#index.push(value);
+                Ok(())
+            }
+        }
+    });
+    let gen_collections_mut = quote! {
+        #(impl #impl_generics #krate GetAllMut<#mut_element_types> for #name #ty_generics #where_clause {
+            fn get_all_mut(&mut self) -> &mut [#mut_element_types] {
+                &mut self.#mut_collection_indices
+            }
+        })*
+        #(impl #impl_generics #krate Push<#mut_element_types> for #name #ty_generics #where_clause {
+            fn push(&mut self, value: #mut_element_types) -> Result<(), #mut_element_types> {
+                #pushes
+            }
+        })*
+    };
+    let gen_collections = quote! {
+        #(impl #impl_generics #krate GetAll<#element_types> for #name #ty_generics #where_clause {
+            fn get_all(&self) -> &[#element_types] {
+                &self.#collection_indices
+            }
+        })*
+        #gen_collections_mut
+    };
+
+    // atomics can be loaded and stored by the type of their value
+    let (atomic_types, atomic_indices): (Vec<_>, Vec<_>) = ref_fields
+        .iter()
+        .filter(|f| f.options.atomic)
+        .map(|f| {
+            let value_ty = ty::atomic_value_type(f.key_ty()).ok_or_else(|| {
+                syn::Error::new_spanned(
+                    f.ty,
+                    "Typemap option `atomic` requires a field of an atomic type, like `AtomicU32`!",
+                )
+            })?;
+            Ok((value_ty, &f.member))
+        })
+        .collect::<syn::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    let gen_atomics = quote! {
+        #(impl #impl_generics #krate AtomicGet<#atomic_types> for #name #ty_generics #where_clause {
+            fn load(&self, order: ::core::sync::atomic::Ordering) -> #atomic_types {
+                self.#atomic_indices.load(order)
+            }
+            fn store(&self, value: #atomic_types, order: ::core::sync::atomic::Ordering) {
+                self.#atomic_indices.store(value, order)
+            }
+        })*
+    };
+
+    // embassy-sync mutexes can be locked by the type of their value
+    #[cfg(feature = "embassy")]
+    let gen_mutexes = {
+        let mut mutex_types = vec![];
+        let mut mutex_indices = vec![];
+        for f in &ref_fields {
+            match ty::mutex_types(f.key_ty(), f.options.embassy) {
+                Some(types) => {
+                    mutex_types.push(types);
+                    mutex_indices.push(&f.member);
+                }
+                None if f.options.embassy => {
+                    return Err(syn::Error::new_spanned(
+                        f.ty,
+                        "Typemap option `embassy` expects a mutex, like `Mutex<M, T>`!",
+                    ))
+                }
+                None => (),
+            }
+        }
+        let (raw_types, value_types): (Vec<_>, Vec<_>) = mutex_types.into_iter().unzip();
         Some(quote! {
-            #(impl #generics GetMut<#types> for #name #generics {
-                fn get_mut(&mut self) -> &mut #types {
-                    &mut self.#indices
+            #(impl #impl_generics #krate MutexGet<#raw_types, #value_types> for #name #ty_generics #where_clause {
+                fn mutex(&self) -> &#krate __private::embassy_sync::mutex::Mutex<#raw_types, #value_types> {
+                    &self.#mutex_indices
                 }
             })*
         })
+    };
+    #[cfg(not(feature = "embassy"))]
+    let gen_mutexes: Option<proc_macro2::TokenStream> =
+        match ref_fields.iter().find(|f| f.options.embassy) {
+            Some(f) => {
+                return Err(syn::Error::new_spanned(
+                    f.ty,
+                    "Typemap option `embassy` requires the `embassy` feature!",
+                ))
+            }
+            None => None,
+        };
+
+    let gen_bytes = if options.bytes {
+        Some(impl_bytes(ast, &options, &fields))
     } else {
         None
     };
 
-    quote! {
+    let gen_mem_usage = if options.mem_usage {
+        Some(impl_mem_usage(ast, &options, &fields))
+    } else {
+        None
+    };
+
+    let gen_ffi = if options.ffi {
+        Some(impl_ffi(ast, &options, &fields)?)
+    } else {
+        None
+    };
+
+    let gen_extract = if options.extract {
+        Some(impl_extract(ast, &options, &fields))
+    } else {
+        None
+    };
+
+    let gen_reset = if options.reset {
+        Some(impl_reset(ast, &fields))
+    } else {
+        None
+    };
+
+    let gen_any = if options.any {
+        Some(impl_any(ast, &options, &ref_fields))
+    } else {
+        None
+    };
+
+    let gen_fingerprint = if options.fingerprint {
+        Some(impl_fingerprint(ast, &options, &fields))
+    } else {
+        None
+    };
+    let gen_cell = if options.cell {
+        Some(impl_cell(ast, &options, &fields))
+    } else {
+        None
+    };
+    let gen_put = if options.put {
+        if mut_types.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "Typemap option `put` requires mutable fields!",
+            ));
+        }
+        Some(quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Assign `value` to the field of its type, returning `self` for chaining
+                pub fn put<__T>(&mut self, value: __T) -> &mut Self
+                where
+                    Self: #krate Set<__T>,
+                {
+                    #krate Set::set(self, value);
+                    self
+                }
+            }
+        })
+    } else {
+        None
+    };
+    let gen_asref = if options.asref {
+        Some(quote! {
+            #(impl #impl_generics ::core::convert::AsRef<#types> for #name #ty_generics #where_clause {
+                fn as_ref(&self) -> &#types {
+                    &self.#indices
+                }
+            })*
+            #(impl #impl_generics ::core::convert::AsMut<#mut_types> for #name #ty_generics #where_clause {
+                fn as_mut(&mut self) -> &mut #mut_types {
+                    &mut self.#mut_indices
+                }
+            })*
+        })
+    } else {
+        None
+    };
+    let gen_borrow = if options.borrow {
+        Some(quote! {
+            #(impl #impl_generics ::core::borrow::Borrow<#types> for #name #ty_generics #where_clause {
+                fn borrow(&self) -> &#types {
+                    &self.#indices
+                }
+            })*
+            #(impl #impl_generics ::core::borrow::BorrowMut<#mut_types> for #name #ty_generics #where_clause {
+                fn borrow_mut(&mut self) -> &mut #mut_types {
+                    &mut self.#mut_indices
+                }
+            })*
+        })
+    } else {
+        None
+    };
+    let gen_into_ref = if options.into_ref {
+        Some(impl_into_ref(ast, &ref_fields))
+    } else {
+        None
+    };
+    let gen_tuple = if options.tuple {
+        Some(impl_tuple(ast, &fields))
+    } else {
+        None
+    };
+    let gen_into = impl_into(ast, &options, &fields);
+    let gen_from_fields = impl_from_fields(ast, &options, &fields);
+    let gen_inherent = if options.inherent {
+        let gen_inherent_mut = if mut_types.is_empty() && flatten_fields.iter().all(|f| !f.mutable)
+        {
+            None
+        } else {
+            Some(quote! {
+                /// Get a mutable reference to the field of type `__T`
+                pub fn get_mut<__T>(&mut self) -> &mut __T
+                where
+                    Self: #krate GetMut<__T>,
+                {
+                    #krate GetMut::get_mut(self)
+                }
+            })
+        };
+        Some(quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Get a reference to the field of type `__T`
+                pub fn get<__T>(&self) -> &__T
+                where
+                    Self: #krate Get<__T>,
+                {
+                    #krate Get::get(self)
+                }
+                #gen_inherent_mut
+            }
+        })
+    } else {
+        None
+    };
+    // as the field types appear in an associated type, they must be as visible as the struct
+    let gen_positional = if options.positional {
+        let positions = ref_fields.iter().map(|f| f.index.index as usize);
+        let position_types: Vec<_> = ref_fields.iter().map(|f| f.ty).collect();
+        Some(quote! {
+            #(impl #impl_generics #krate GetAt<#positions> for #name #ty_generics #where_clause {
+                type Output = #position_types;
+                fn get_at(&self) -> &#position_types {
+                    &self.#indices
+                }
+            })*
+        })
+    } else {
+        None
+    };
+    let gen_types = if options.types {
+        let all_types: Vec<_> = fields.iter().map(|f| f.ty).collect();
+        let len = all_types.len();
+        Some(quote! {
+            impl #impl_generics #krate Typemap for #name #ty_generics #where_clause {
+                type Types = (#(#all_types,)*);
+
+                const LEN: usize = #len;
+
+                fn type_names() -> impl ::core::iter::ExactSizeIterator<Item = &'static str> {
+                    [#(::core::any::type_name::<#all_types>()),*].into_iter()
+                }
+            }
+        })
+    } else {
+        None
+    };
+    let gen_pin = if options.pin {
+        if mut_types.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "Typemap option `pin` requires mutable fields!",
+            ));
+        }
+        Some(impl_pin(ast, &options, &mut_fields))
+    } else {
+        None
+    };
+    // all ordered pairs of distinct fields can be borrowed simultaneously
+    let gen_disjoint = if options.disjoint {
+        let pairs = mut_fields.iter().flat_map(|a| {
+            mut_fields
+                .iter()
+                .filter(move |b| a.index != b.index)
+                .map(move |b| (a, b))
+        });
+        let impls = pairs.map(|(a, b)| {
+            let (a_ty, b_ty) = (a.key_ty(), b.key_ty());
+            let (a_index, b_index) = (&a.member, &b.member);
+            quote! {
+                impl #impl_generics #krate GetDisjointMut<#a_ty, #b_ty> for #name #ty_generics #where_clause {
+                    fn get_disjoint_mut(&mut self) -> (&mut #a_ty, &mut #b_ty) {
+                        (&mut self.#a_index, &mut self.#b_index)
+                    }
+                }
+            }
+        });
+        Some(quote! { #(#impls)* })
+    } else {
+        None
+    };
+    let gen_query = if options.query {
+        let impls = ref_fields.iter().enumerate().map(|(position, f)| {
+            let ty = f.key_ty();
+            let index = &f.member;
+            let gen_mut = f.mutable.then(|| {
+                quote! {
+                    unsafe impl #impl_generics #krate QueryFieldMut<#ty> for #name #ty_generics #where_clause {}
+                }
+            });
+            quote! {
+                unsafe impl #impl_generics #krate QueryField<#ty> for #name #ty_generics #where_clause {
+                    const INDEX: usize = #position;
+                    unsafe fn field_ptr(map: *mut Self) -> *mut #ty {
+                        // only the field is borrowed, so that references to other fields stay valid
+                        let field: &mut #ty = unsafe { &mut (*map).#index };
+                        field
+                    }
+                }
+                #gen_mut
+            }
+        });
+        Some(quote! { #(#impls)* })
+    } else {
+        None
+    };
+    let gen_visit = if options.visit {
+        Some(quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Call `visitor` on all the fields, in declaration order
+                pub fn accept<__V>(&self, visitor: &mut __V)
+                where
+                    __V: ?Sized #(+ #krate TypemapVisitor<#types>)*,
+                {
+                    #(#krate TypemapVisitor::<#types>::visit(visitor, &self.#indices);)*
+                }
+            }
+        })
+    } else {
+        None
+    };
+    if options.rayon && options.dyn_traits.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "Typemap option `rayon` requires traits given with the `dyn` option!",
+        ));
+    }
+    let gen_dyn = options.dyn_traits.iter().map(|tr| {
+        let last = &tr.segments.last().unwrap().ident;
+        let method = format_ident!("for_each_{}", ty::snake_case(&last.to_string()));
+        let doc = format!(
+            "Call `f` on all the fields as `&dyn {}`, in declaration order",
+            last
+        );
+        let map_method = format_ident!("map_{}", ty::snake_case(&last.to_string()));
+        let map_doc = format!(
+            "Call `f` on all the fields as `&dyn {}`, returning each result with the name of the field type, in declaration order",
+            last
+        );
+        let len = types.len();
+        let gen_par = if options.rayon {
+            Some(impl_rayon(ast, &options, tr, &ref_fields, &mut_fields))
+        } else {
+            None
+        };
+        quote! {
+            #gen_par
+
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #doc]
+                pub fn #method(&self, mut f: impl FnMut(&dyn #tr))
+                where
+                    #(#types: #tr,)*
+                {
+                    #({
+                        let value: &#types = &self.#indices;
+                        f(value);
+                    })*
+                }
+
+                #[doc = #map_doc]
+                pub fn #map_method<__R>(
+                    &self,
+                    mut f: impl FnMut(&dyn #tr) -> __R,
+                ) -> [(&'static str, __R); #len]
+                where
+                    #(#types: #tr,)*
+                {
+                    [#({
+                        let value: &#types = &self.#indices;
+                        (::core::any::type_name::<#types>(), f(value))
+                    }),*]
+                }
+            }
+        }
+    });
+    let gen_split = if options.split {
+        if mut_types.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "Typemap option `split` requires mutable fields!",
+            ));
+        }
+        Some(quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Mutably borrow all the mutable fields at once
+                pub fn split_mut(&mut self) -> (#(&mut #mut_types,)*) {
+                    (#(&mut self.#mut_indices,)*)
+                }
+            }
+        })
+    } else {
+        None
+    };
+    let gen_view = if options.view {
+        Some(impl_view(ast, &options, &ref_fields))
+    } else {
+        None
+    };
+    let gen_view_mut = if options.view_mut {
+        if mut_types.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "Typemap option `view_mut` requires mutable fields!",
+            ));
+        }
+        Some(impl_view_mut(ast, &options, &ref_fields))
+    } else {
+        None
+    };
+    let gen_alias = options
+        .alias
+        .as_ref()
+        .map(|alias| impl_alias(ast, &options, alias, &types));
+    let gen_freeze = if options.freeze {
+        Some(quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Turn the typemap into a cheaply cloneable immutable one
+                pub fn freeze(self) -> #krate Frozen<Self> {
+                    #krate Frozen::new(self)
+                }
+            }
+        })
+    } else {
+        None
+    };
+    let gen_display = if options.display {
+        Some(impl_display(ast, &options, &fields))
+    } else {
+        None
+    };
+
+    let gen_commands = options
+        .commands
+        .as_ref()
+        .map(|derives| impl_commands(ast, &options, &fields, derives));
+
+    let gen_serde = if options.serde {
+        Some(serde::impl_serde(ast, &options, &fields)?)
+    } else {
+        None
+    };
+
+    let gen_rhai = if options.rhai {
+        Some(rhai::impl_rhai(ast, &options, &ref_fields)?)
+    } else {
+        None
+    };
+
+    let gen_env = if fields.iter().any(|f| f.options.env.is_some()) {
+        Some(impl_env(ast, &options, &fields))
+    } else {
+        None
+    };
+
+    let gen_bevy = if options.bevy {
+        Some(impl_bevy(ast, &options, &fields))
+    } else {
+        None
+    };
+
+    let gen_type_map = if options.type_map {
+        Some(impl_type_map(ast, &options, &fields))
+    } else {
+        None
+    };
+
+    let gen_frunk = if options.frunk {
+        Some(impl_frunk(ast, &options, &fields, &mut_fields))
+    } else {
+        None
+    };
+
+    let gen_size_checks = impl_size_checks(ast, &options, &fields)?;
+
+    #[cfg(feature = "manifest")]
+    let gen_manifest = Some(impl_manifest(ast, &options, &fields));
+    #[cfg(not(feature = "manifest"))]
+    let gen_manifest: Option<proc_macro2::TokenStream> = None;
+
+    Ok(quote! {
         #gen
         #gen_mut
+        #gen_flatten
+        #gen_try_get
+        #gen_try_get_mut
+        #gen_collections
+        #gen_atomics
+        #gen_mutexes
+        #gen_bytes
+        #gen_mem_usage
+        #gen_ffi
+        #gen_extract
+        #gen_reset
+        #gen_any
+        #gen_fingerprint
+        #gen_display
+        #gen_cell
+        #gen_put
+        #gen_inherent
+        #gen_positional
+        #gen_types
+        #gen_pin
+        #gen_disjoint
+        #gen_query
+        #gen_visit
+        #(#gen_dyn)*
+        #gen_split
+        #gen_view
+        #gen_view_mut
+        #gen_alias
+        #gen_asref
+        #gen_borrow
+        #gen_into_ref
+        #gen_tuple
+        #gen_into
+        #gen_from_fields
+        #gen_freeze
+        #gen_commands
+        #gen_serde
+        #gen_rhai
+        #gen_env
+        #gen_bevy
+        #gen_frunk
+        #gen_type_map
+        #gen_size_checks
+        #gen_manifest
+    })
+}
+
+fn impl_ffi(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> syn::Result<proc_macro2::TokenStream> {
+    let is_repr_c = ast.attrs.iter().any(|attr| match attr.parse_meta() {
+        Ok(syn::Meta::List(list)) if list.path.is_ident("repr") => list.nested.iter().any(
+            |repr| matches!(repr, syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("C")),
+        ),
+        _ => false,
+    });
+    if !is_repr_c {
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "Typemap option `ffi` requires `#[repr(C)]` on the struct!",
+        ));
+    }
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &ast.generics,
+            "Typemap option `ffi` does not support generic structs!",
+        ));
+    }
+    if let Some(field) = fields.iter().find(|f| f.options.boxed) {
+        return Err(syn::Error::new_spanned(
+            field.ty,
+            "Typemap option `ffi` cannot be used with boxed fields, as they point to the heap!",
+        ));
+    }
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let types: Vec<_> = fields.iter().map(|f| f.ty).collect();
+    let indices = fields.iter().map(|f| &f.member);
+    let type_names = fields.iter().map(|f| ty::type_name(f.ty));
+    let count = fields.len();
+    let checks = types.iter().map(|ty| {
+        quote_spanned! { ty.span() => assert_ffi_safe::<#ty>(); }
+    });
+    Ok(quote! {
+        const _: () = {
+            const fn assert_ffi_safe<T: #krate FfiSafe>() {}
+            #(#checks)*
+        };
+        // SAFETY: the struct is repr(C) and all its fields are checked above to be FfiSafe
+        unsafe impl #krate FfiSafe for #name {}
+        impl #name {
+            /// Layout of the fields, in declaration order
+            pub const LAYOUT: [#krate FieldLayout; #count] = [
+                #(#krate FieldLayout {
+                    name: #type_names,
+                    offset: ::core::mem::offset_of!(#name, #indices),
+                    size: ::core::mem::size_of::<#types>(),
+                    align: ::core::mem::align_of::<#types>(),
+                }),*
+            ];
+        }
+    })
+}
+
+fn impl_extract(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let members = fields.iter().map(|f| &f.member);
+    let extracted: Vec<_> = fields.iter().filter(|f| !f.options.skip).collect();
+    let key_types: Vec<_> = extracted.iter().map(|f| f.key_ty()).collect();
+    // wrapped fields are rebuilt from their key type, skipped ones get their default value
+    let values = fields.iter().map(|f| {
+        let key_ty = f.key_ty();
+        let value = quote! {
+            ::core::clone::Clone::clone(<__M as #krate Get<#key_ty>>::get(map))
+        };
+        if f.options.skip {
+            quote! { ::core::default::Default::default() }
+        } else if f.options.boxed || f.options.pad {
+            quote! { ::core::convert::From::from(#value) }
+        } else {
+            value
+        }
+    });
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Build a typemap by cloning its fields from `map`, which may contain other types
+            pub fn extracted_from<__M>(map: &__M) -> Self
+            where
+                #(__M: #krate Get<#key_types>,)*
+                #(#key_types: ::core::clone::Clone,)*
+            {
+                #name { #(#members: #values),* }
+            }
+        }
+    }
+}
+
+fn impl_tuple(ast: &syn::DeriveInput, fields: &[Field]) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let types: Vec<_> = fields.iter().map(|f| f.ty).collect();
+    let members: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    let indices: Vec<_> = fields.iter().map(|f| &f.index).collect();
+    quote! {
+        impl #impl_generics ::core::convert::From<(#(#types,)*)> for #name #ty_generics #where_clause {
+            fn from(tuple: (#(#types,)*)) -> Self {
+                #name { #(#members: tuple.#indices),* }
+            }
+        }
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for (#(#types,)*) #where_clause {
+            fn from(map: #name #ty_generics) -> Self {
+                (#(map.#members,)*)
+            }
+        }
+    }
+}
+
+fn impl_into(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    if options.into.is_empty() {
+        return quote! {};
+    }
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let converted: Vec<_> = fields.iter().filter(|f| !f.options.skip).collect();
+    let members: Vec<_> = converted.iter().map(|f| &f.member).collect();
+    let types: Vec<_> = converted.iter().map(|f| f.ty).collect();
+    let gen_take = converted.iter().enumerate().map(|(i, f)| {
+        let ty = f.ty;
+        let position = syn::Index::from(i);
+        let mut take_generics = ast.generics.clone();
+        take_generics.params.push(parse_quote!(__U));
+        take_generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(__U: ::core::convert::From<#ty>));
+        let (take_impl_generics, _, take_where_clause) = take_generics.split_for_impl();
+        quote! {
+            impl #take_impl_generics #krate TakeField<__U, #krate FieldIndex<#i>> for #name #ty_generics #take_where_clause {
+                fn take_field(fields: &mut Self::Fields) -> __U {
+                    let field = fields.#position.take().expect("the field has already been taken");
+                    ::core::convert::From::from(field)
+                }
+            }
+        }
+    });
+    // the target finds the field converted into each of its own ones by its type
+    let impls = options.into.iter().map(|target| {
+        quote! {
+            impl #impl_generics ::core::convert::From<#name #ty_generics> for #target #where_clause {
+                fn from(map: #name #ty_generics) -> Self {
+                    <Self as #krate FromFields<#name #ty_generics, _>>::from_fields(map)
+                }
+            }
+        }
+    });
+    quote! {
+        impl #impl_generics #krate IntoFields for #name #ty_generics #where_clause {
+            type Fields = (#(::core::option::Option<#types>,)*);
+
+            fn into_fields(self) -> Self::Fields {
+                (#(::core::option::Option::Some(self.#members),)*)
+            }
+        }
+        #(#gen_take)*
+        #(#impls)*
+    }
+}
+
+fn impl_from_fields(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> Option<proc_macro2::TokenStream> {
+    // the other skipped fields have no value to be built from
+    let (skipped, converted): (Vec<_>, Vec<_>) = fields.iter().partition(|f| f.options.skip);
+    if skipped
+        .iter()
+        .any(|f| ty::wrapped_type(f.ty, "PhantomData").is_none())
+    {
+        return None;
+    }
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+    let members: Vec<_> = converted.iter().map(|f| &f.member).collect();
+    let types: Vec<_> = converted.iter().map(|f| f.ty).collect();
+    let skipped_members = skipped.iter().map(|f| &f.member);
+    let source_indices: Vec<_> = (0..converted.len())
+        .map(|i| format_ident!("__I{}", i))
+        .collect();
+    let mut from_generics = ast.generics.clone();
+    from_generics.params.push(parse_quote!(__M));
+    for index in &source_indices {
+        from_generics.params.push(parse_quote!(const #index: usize));
+    }
+    let predicates = &mut from_generics.make_where_clause().predicates;
+    predicates.push(parse_quote!(__M: #krate IntoFields));
+    for (ty, index) in types.iter().zip(&source_indices) {
+        predicates.push(parse_quote!(__M: #krate TakeField<#ty, #krate FieldIndex<#index>>));
+    }
+    let (from_impl_generics, _, from_where_clause) = from_generics.split_for_impl();
+    Some(quote! {
+        impl #from_impl_generics #krate FromFields<__M, (#(#krate FieldIndex<#source_indices>,)*)>
+            for #name #ty_generics #from_where_clause
+        {
+            #[allow(unused_mut, unused_variables)]
+            fn from_fields(map: __M) -> Self {
+                const {
+                    ::core::assert!(
+                        #krate __private::distinct_indices(&[#(#source_indices),*]),
+                        "a field of the source typemap cannot be converted into several fields of the target one"
+                    )
+                };
+                let mut fields = <__M as #krate IntoFields>::into_fields(map);
+                #name {
+                    #(#members: <__M as #krate TakeField<#types, #krate FieldIndex<#source_indices>>>::take_field(&mut fields),)*
+                    #(#skipped_members: ::core::marker::PhantomData,)*
+                }
+            }
+        }
+    })
+}
+
+fn impl_reset(ast: &syn::DeriveInput, fields: &[Field]) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let option_indices = fields
+        .iter()
+        .filter(|f| ty::wrapped_type(f.ty, "Option").is_some())
+        .map(|f| &f.member);
+    let indices = fields.iter().map(|f| &f.member);
+    let values = fields.iter().map(|f| match &f.options.default {
+        Some(default) => quote! { (#default) },
+        None => quote! { ::core::default::Default::default() },
+    });
+    // the default values of fields depending on generic parameters might not exist for all of them
+    let default_types = fields
+        .iter()
+        .filter(|f| f.options.default.is_none())
+        .map(|f| f.ty);
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Set all optional fields to `None`, leaving the others untouched
+            pub fn clear_all(&mut self) {
+                #(self.#option_indices = None;)*
+            }
+
+            /// Set all fields to their declared value, or to their default one
+            pub fn reset(&mut self)
+            where
+                #(#default_types: ::core::default::Default,)*
+            {
+                #(self.#indices = #values;)*
+            }
+        }
+    }
+}
+
+fn impl_any(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[&Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // wrapped fields are looked up by their key type
+    let derefs: Vec<_> = fields
+        .iter()
+        .map(|f| (f.options.boxed || f.options.pad).then(|| quote! { * }))
+        .collect();
+    let indices: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    let types: Vec<_> = fields.iter().map(|f| f.key_ty()).collect();
+    let (mut_derefs, mut_indices): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .zip(&derefs)
+        .filter(|(f, _)| f.mutable)
+        .map(|(f, deref)| (deref, &f.member))
+        .unzip();
+    let mut_types: Vec<_> = fields
+        .iter()
+        .filter(|f| f.mutable)
+        .map(|f| f.key_ty())
+        .collect();
+    let get_dyn_mut = if !mut_indices.is_empty() {
+        quote! { self.get_any_mut(id) }
+    } else {
+        quote! { None }
+    };
+    let len = fields.len();
+    let type_names = types.iter().enumerate().map(|(i, ty)| {
+        quote! { #i => Some(::core::any::type_name::<#ty>()), }
+    });
+    let (lookup_mut, iter_any_mut) = if !mut_indices.is_empty() {
+        (
+            quote! {
+                #(if let Some(value) = (&mut #mut_derefs self.#mut_indices as &mut dyn ::core::any::Any).downcast_mut() {
+                    return Some(value);
+                })*
+                None
+            },
+            Some(quote! {
+                /// Iterate over mutable references to all mutable fields, in declaration order
+                pub fn iter_any_mut(&mut self) -> impl Iterator<Item = &mut dyn ::core::any::Any> {
+                    [#(&mut #mut_derefs self.#mut_indices as &mut dyn ::core::any::Any),*].into_iter()
+                }
+
+                /// Return a mutable reference to the mutable field whose type has identifier `id`, if any
+                pub fn get_any_mut(&mut self, id: ::core::any::TypeId) -> Option<&mut dyn ::core::any::Any> {
+                    #(if id == ::core::any::TypeId::of::<#mut_types>() {
+                        return Some(&mut #mut_derefs self.#mut_indices as &mut dyn ::core::any::Any);
+                    })*
+                    None
+                }
+            }),
+        )
+    } else {
+        (quote! { None }, None)
+    };
+    quote! {
+        impl #impl_generics #krate AnyTypemap for #name #ty_generics #where_clause {
+            fn lookup<__T: ::core::any::Any>(&self) -> Option<&__T> {
+                #(if let Some(value) = (&#derefs self.#indices as &dyn ::core::any::Any).downcast_ref() {
+                    return Some(value);
+                })*
+                None
+            }
+
+            fn lookup_mut<__T: ::core::any::Any>(&mut self) -> Option<&mut __T> {
+                #lookup_mut
+            }
+        }
+
+        impl #impl_generics #krate DynTypemap for #name #ty_generics #where_clause {
+            fn get_dyn(&self, id: ::core::any::TypeId) -> Option<&dyn ::core::any::Any> {
+                self.get_any(id)
+            }
+
+            fn get_dyn_mut(&mut self, id: ::core::any::TypeId) -> Option<&mut dyn ::core::any::Any> {
+                #get_dyn_mut
+            }
+
+            fn field_count(&self) -> usize {
+                #len
+            }
+
+            fn type_name(&self, index: usize) -> Option<&'static str> {
+                match index {
+                    #(#type_names)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Iterate over references to all fields, in declaration order
+            pub fn iter_any(&self) -> impl Iterator<Item = &dyn ::core::any::Any> {
+                [#(&#derefs self.#indices as &dyn ::core::any::Any),*].into_iter()
+            }
+
+            /// Fold all fields into `acc` with `f`, in declaration order
+            pub fn fold_fields<__Acc>(
+                &self,
+                acc: __Acc,
+                f: impl FnMut(__Acc, &dyn ::core::any::Any) -> __Acc,
+            ) -> __Acc {
+                self.iter_any().fold(acc, f)
+            }
+
+            /// Return a reference to the field whose type has identifier `id`, if any
+            pub fn get_any(&self, id: ::core::any::TypeId) -> Option<&dyn ::core::any::Any> {
+                #(if id == ::core::any::TypeId::of::<#types>() {
+                    return Some(&#derefs self.#indices as &dyn ::core::any::Any);
+                })*
+                None
+            }
+
+            #iter_any_mut
+        }
+    }
+}
+
+fn impl_fingerprint(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    // 64-bit FNV-1a, with each type name followed by a zero byte so that names cannot run together
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in fields
+        .iter()
+        .flat_map(|f| ty::type_name(f.ty).into_bytes().into_iter().chain([0]))
+    {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #krate Fingerprint for #name #ty_generics #where_clause {
+            const FINGERPRINT: u64 = #hash;
+        }
+    }
+}
+
+fn impl_cell(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let vis = &ast.vis;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let cell = format_ident!("{}Cell", name);
+    let members: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    // skipped fields are kept as they are, as they have no accessor
+    let (cell_types, wraps, unwraps): (Vec<_>, Vec<_>, Vec<_>) = fields
+        .iter()
+        .map(|f| {
+            let ty = f.ty;
+            let member = &f.member;
+            if f.options.skip {
+                (
+                    quote! { #ty },
+                    quote! { map.#member },
+                    quote! { map.#member },
+                )
+            } else {
+                let wrapper = if f.options.refcell {
+                    quote! { ::core::cell::RefCell }
+                } else {
+                    quote! { ::core::cell::Cell }
+                };
+                (
+                    quote! { #wrapper<#ty> },
+                    quote! { #wrapper::new(map.#member) },
+                    quote! { map.#member.into_inner() },
+                )
+            }
+        })
+        .fold((vec![], vec![], vec![]), |mut acc, (ty, wrap, unwrap)| {
+            acc.0.push(ty);
+            acc.1.push(wrap);
+            acc.2.push(unwrap);
+            acc
+        });
+    let (types, indices): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .zip(&cell_types)
+        .filter(|(f, _)| !f.options.skip)
+        .map(|(f, ty)| (ty, &f.member))
+        .unzip();
+    let gen_cell_get = fields.iter().filter(|f| !f.options.skip).map(|f| {
+        let ty = f.ty;
+        let member = &f.member;
+        let (get, set) = if f.options.refcell {
+            (
+                quote! { *self.#member.borrow() },
+                quote! { *self.#member.borrow_mut() = value },
+            )
+        } else {
+            (
+                quote! { self.#member.get() },
+                quote! { self.#member.set(value) },
+            )
+        };
+        // the bound is higher-ranked so that it is not rejected as trivially false for concrete
+        // types which are not `Copy`, for which `get` is then unavailable
+        quote! {
+            impl #impl_generics #krate CellGet<#ty> for #cell #ty_generics #where_clause {
+                fn get(&self) -> #ty
+                where
+                    for<'__a> #ty: ::core::marker::Copy,
+                {
+                    #get
+                }
+                fn set(&self, value: #ty) {
+                    #set
+                }
+                fn replace(&self, value: #ty) -> #ty {
+                    self.#member.replace(value)
+                }
+            }
+        }
+    });
+    // the companion has the same shape as the struct, so that fields are accessed the same way
+    let cell_struct = match &fields.first().map(|f| &f.member) {
+        Some(syn::Member::Named(_)) => {
+            let names = fields.iter().map(|f| &f.member);
+            quote! { #vis struct #cell #generics #where_clause { #(#names: #cell_types),* } }
+        }
+        _ => quote! { #vis struct #cell #generics(#(#cell_types),*) #where_clause; },
+    };
+    let doc = format!(
+        "Companion of [`{}`] wrapping its fields in cells, to be mutated through shared references",
+        name
+    );
+    quote! {
+        #[doc = #doc]
+        #cell_struct
+        #(impl #impl_generics #krate Get<#types> for #cell #ty_generics #where_clause {
+            fn get(&self) -> &#types {
+                &self.#indices
+            }
+        })*
+        #(#gen_cell_get)*
+        impl #impl_generics #cell #ty_generics #where_clause {
+            /// Get a copy of the value of type `T`
+            pub fn get<__T: ::core::marker::Copy>(&self) -> __T
+            where
+                Self: #krate CellGet<__T>,
+            {
+                #krate CellGet::<__T>::get(self)
+            }
+
+            /// Set the value of type `T`
+            pub fn set<__T>(&self, value: __T)
+            where
+                Self: #krate CellGet<__T>,
+            {
+                #krate CellGet::<__T>::set(self, value)
+            }
+
+            /// Replace the value of type `T`, returning the previous one
+            pub fn replace<__T>(&self, value: __T) -> __T
+            where
+                Self: #krate CellGet<__T>,
+            {
+                #krate CellGet::<__T>::replace(self, value)
+            }
+        }
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for #cell #ty_generics #where_clause {
+            fn from(map: #name #ty_generics) -> Self {
+                #cell { #(#members: #wraps),* }
+            }
+        }
+        impl #impl_generics ::core::convert::From<#cell #ty_generics> for #name #ty_generics #where_clause {
+            fn from(map: #cell #ty_generics) -> Self {
+                #name { #(#members: #unwraps),* }
+            }
+        }
+    }
+}
+
+fn impl_view(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[&Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let vis = &ast.vis;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let view = format_ident!("{}Ref", name);
+    let mut view_generics = ast.generics.clone();
+    view_generics.params.insert(0, parse_quote!('__a));
+    let (view_impl_generics, view_ty_generics, _) = view_generics.split_for_impl();
+    let types: Vec<_> = fields.iter().map(|f| f.key_ty()).collect();
+    let members = fields.iter().map(|f| &f.member);
+    // the view only holds the fields accessible by reference, so its indices differ
+    let indices = (0..fields.len()).map(syn::Index::from);
+    let doc = format!(
+        "View of [`{}`] borrowing its fields, cheaply copyable and implementing `Get` for their types",
+        name
+    );
+    quote! {
+        #[doc = #doc]
+        #vis struct #view #view_generics(#(&'__a #types),*) #where_clause;
+        impl #view_impl_generics ::core::clone::Clone for #view #view_ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+        impl #view_impl_generics ::core::marker::Copy for #view #view_ty_generics #where_clause {}
+        #(impl #view_impl_generics #krate Get<#types> for #view #view_ty_generics #where_clause {
+            fn get(&self) -> &#types {
+                self.#indices
+            }
+        })*
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Borrow all the fields as a cheaply copyable view
+            pub fn as_view<'__a>(&'__a self) -> #view #view_ty_generics {
+                #view(#(&self.#members),*)
+            }
+        }
+    }
+}
+
+fn impl_view_mut(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[&Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let vis = &ast.vis;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let view = format_ident!("{}Mut", name);
+    let mut view_generics = ast.generics.clone();
+    view_generics.params.insert(0, parse_quote!('__a));
+    let (view_impl_generics, view_ty_generics, _) = view_generics.split_for_impl();
+    let types: Vec<_> = fields.iter().map(|f| f.key_ty()).collect();
+    let members = fields.iter().map(|f| &f.member);
+    // the view only holds the fields accessible by reference, so its indices differ
+    let indices = (0..fields.len()).map(syn::Index::from);
+    let (mut_types, mut_indices): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.mutable)
+        .map(|(i, f)| (f.key_ty(), syn::Index::from(i)))
+        .unzip();
+    let view_types = fields.iter().map(|f| {
+        let ty = f.key_ty();
+        if f.mutable {
+            quote! { &'__a mut #ty }
+        } else {
+            quote! { &'__a #ty }
+        }
+    });
+    let borrows = fields.iter().map(|f| {
+        if f.mutable {
+            quote! { &mut }
+        } else {
+            quote! { & }
+        }
+    });
+    let doc = format!(
+        "View of [`{}`] mutably borrowing its fields, implementing `Get` and `GetMut` for their types",
+        name
+    );
+    quote! {
+        #[doc = #doc]
+        #vis struct #view #view_generics(#(#view_types),*) #where_clause;
+        #(impl #view_impl_generics #krate Get<#types> for #view #view_ty_generics #where_clause {
+            fn get(&self) -> &#types {
+                self.#indices
+            }
+        })*
+        #(impl #view_impl_generics #krate GetMut<#mut_types> for #view #view_ty_generics #where_clause {
+            fn get_mut(&mut self) -> &mut #mut_types {
+                self.#mut_indices
+            }
+        })*
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Mutably borrow all the fields as a view
+            pub fn as_view_mut<'__a>(&'__a mut self) -> #view #view_ty_generics {
+                #view(#(#borrows self.#members),*)
+            }
+        }
+    }
+}
+
+fn impl_display(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // volatile fields are not read, so only their type is shown
+    let gen_fields = fields
+        .iter()
+        .filter(|f| !f.options.skip)
+        .enumerate()
+        .map(|(i, f)| {
+            let separator = if i == 0 { "" } else { ", " };
+            let type_name = ty::type_name(f.key_ty());
+            let gen_value = if f.options.volatile {
+                None
+            } else {
+                let key_ty = f.key_ty();
+                let index = &f.member;
+                Some(quote! {
+                    (&#krate __private::DisplayProbe::<#key_ty>(&self.#index)).fmt_value(f)?;
+                })
+            };
+            quote! {
+                f.write_str(#separator)?;
+                f.write_str(#type_name)?;
+                #gen_value
+            }
+        });
+    let open = format!("{}(", name);
+    quote! {
+        impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #[allow(unused_imports)]
+                use #krate __private::{DisplayFallback as _, DisplayViaDisplay as _};
+                f.write_str(#open)?;
+                #(#gen_fields)*
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+fn impl_env(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let members = fields.iter().map(|f| &f.member);
+    let variables: Vec<_> = fields
+        .iter()
+        .map(|f| format_ident!("field_{}", f.index))
+        .collect();
+    // all variables are read before failing, so that all problems are reported at once
+    let reads = fields.iter().zip(&variables).map(|(f, variable)| {
+        let ty = f.ty;
+        match (&f.options.env, &f.options.parse_with) {
+            (Some(env), Some(parse_with)) => quote! {
+                let #variable = #krate __private::env_field(&mut errors, &mut lookup, #env, #parse_with);
+            },
+            (Some(env), None) => quote! {
+                let #variable = #krate __private::env_field(&mut errors, &mut lookup, #env, str::parse::<#ty>);
+            },
+            (None, _) => quote! {
+                let #variable = Some(<#ty as ::core::default::Default>::default());
+            },
+        }
+    });
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Build the typemap from environment variables, reporting all missing or invalid ones
+            pub fn from_env() -> Result<Self, #krate EnvError> {
+                Self::from_env_with(#krate __private::env_var)
+            }
+
+            /// Build the typemap from variables given by `lookup`, reporting all missing or invalid ones
+            pub fn from_env_with<__S: ::core::convert::AsRef<str>>(
+                mut lookup: impl FnMut(&str) -> Option<__S>,
+            ) -> Result<Self, #krate EnvError> {
+                let mut errors = #krate EnvError::default();
+                #(#reads)*
+                match (#(#variables,)*) {
+                    (#(Some(#variables),)*) => Ok(#name { #(#members: #variables),* }),
+                    _ => Err(errors),
+                }
+            }
+        }
+    }
+}
+
+fn impl_bevy(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let bevy = quote! { #krate __private::bevy_ecs };
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let members = fields.iter().map(|f| &f.member);
+    let resources: Vec<_> = fields.iter().filter(|f| !f.options.skip).collect();
+    let types: Vec<_> = resources.iter().map(|f| f.ty).collect();
+    let indices = resources.iter().map(|f| &f.member);
+    // skipped fields are not resources, so they get their default value when extracted
+    let values = fields.iter().map(|f| {
+        let ty = f.ty;
+        if f.options.skip {
+            quote! { ::core::default::Default::default() }
+        } else {
+            quote! { world.get_resource::<#ty>()?.clone() }
+        }
+    });
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Insert every field into `world` as a resource, replacing existing ones
+            pub fn insert_into_world(self, world: &mut #bevy::world::World)
+            where
+                #(#types: #bevy::resource::Resource,)*
+            {
+                #(world.insert_resource(self.#indices);)*
+            }
+
+            /// Clone the resources of `world` into a typemap, if they are all present
+            pub fn extract_from_world(world: &#bevy::world::World) -> Option<Self>
+            where
+                #(#types: #bevy::resource::Resource + ::core::clone::Clone,)*
+            {
+                Some(#name { #(#members: #values),* })
+            }
+        }
+    }
+}
+
+fn impl_rayon(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    tr: &syn::Path,
+    fields: &[&Field],
+    mut_fields: &[&&Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let rayon = quote! { #krate __private::rayon };
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let last = &tr.segments.last().unwrap().ident;
+    let snake = ty::snake_case(&last.to_string());
+    let method = format_ident!("par_for_each_{}", snake);
+    let doc = format!(
+        "Call `f` on all the fields as `&dyn {}`, in parallel on the rayon thread pool",
+        last
+    );
+    let types: Vec<_> = fields.iter().map(|f| f.key_ty()).collect();
+    let indices = fields.iter().map(|f| &f.member);
+    let gen_mut = if !mut_fields.is_empty() {
+        let method = format_ident!("par_for_each_{}_mut", snake);
+        let doc = format!(
+            "Call `f` on all the mutable fields as `&mut dyn {}`, in parallel on the rayon thread pool",
+            last
+        );
+        let types: Vec<_> = mut_fields.iter().map(|f| f.key_ty()).collect();
+        let indices = mut_fields.iter().map(|f| &f.member);
+        Some(quote! {
+            #[doc = #doc]
+            pub fn #method(&mut self, f: impl Fn(&mut dyn #tr) + ::core::marker::Sync)
+            where
+                #(#types: #tr + ::core::marker::Send,)*
+            {
+                let f = &f;
+                #rayon::scope(|scope| {
+                    #(scope.spawn(|_| {
+                        let value: &mut #types = &mut self.#indices;
+                        f(value)
+                    });)*
+                });
+            }
+        })
+    } else {
+        None
+    };
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #[doc = #doc]
+            pub fn #method(&self, f: impl Fn(&dyn #tr) + ::core::marker::Sync)
+            where
+                #(#types: #tr + ::core::marker::Sync,)*
+            {
+                let f = &f;
+                #rayon::scope(|scope| {
+                    #(scope.spawn(|_| {
+                        let value: &#types = &self.#indices;
+                        f(value)
+                    });)*
+                });
+            }
+
+            #gen_mut
+        }
+    }
+}
+
+fn impl_type_map(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let inserted: Vec<_> = fields.iter().filter(|f| !f.options.skip).collect();
+    let types: Vec<_> = inserted.iter().map(|f| f.key_ty()).collect();
+    // wrapped fields are inserted by their key type, as they are looked up
+    let values = inserted.iter().map(|f| {
+        let member = &f.member;
+        if f.options.boxed {
+            quote! { *self.#member }
+        } else if f.options.pad {
+            quote! { #krate CachePadded::into_inner(self.#member) }
+        } else {
+            quote! { self.#member }
+        }
+    });
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Convert into a runtime typemap, inserting every field by its type
+            pub fn into_type_map(self) -> #krate __private::type_map::TypeMap
+            where
+                #(#types: 'static,)*
+            {
+                let mut map = #krate __private::type_map::TypeMap::new();
+                #(map.insert::<#types>(#values);)*
+                map
+            }
+        }
+    }
+}
+
+fn impl_frunk(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+    mut_fields: &[&&Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let frunk = quote! { #krate __private::frunk_core };
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let members: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    let bindings: Vec<_> = fields
+        .iter()
+        .map(|f| format_ident!("field_{}", f.index))
+        .collect();
+    // HLists nest from the last field
+    let repr = fields
+        .iter()
+        .rev()
+        .fold(quote! { #frunk::hlist::HNil }, |tail, f| {
+            let ty = f.ty;
+            quote! { #frunk::hlist::HCons<#ty, #tail> }
+        });
+    let cons = |values: &[proc_macro2::TokenStream]| {
+        values.iter().rev().fold(
+            quote! { #frunk::hlist::HNil },
+            |tail, head| quote! { #frunk::hlist::HCons { head: #head, tail: #tail } },
+        )
+    };
+    let into = cons(
+        &members
+            .iter()
+            .map(|m| quote! { self.#m })
+            .collect::<Vec<_>>(),
+    );
+    let pattern = cons(&bindings.iter().map(|b| quote! { #b }).collect::<Vec<_>>());
+    let selectors = mut_fields.iter().map(|f| {
+        let ty = f.key_ty();
+        let index = &f.member;
+        quote! {
+            impl #impl_generics #frunk::hlist::Selector<#ty, #frunk::indices::Here> for #name #ty_generics #where_clause {
+                fn get(&self) -> &#ty {
+                    &self.#index
+                }
+                fn get_mut(&mut self) -> &mut #ty {
+                    &mut self.#index
+                }
+            }
+        }
+    });
+    quote! {
+        impl #impl_generics #frunk::generic::Generic for #name #ty_generics #where_clause {
+            type Repr = #repr;
+
+            fn into(self) -> #repr {
+                #into
+            }
+
+            fn from(repr: #repr) -> Self {
+                let #pattern = repr;
+                #name { #(#members: #bindings),* }
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Convert into the HList of all fields, in declaration order
+            pub fn into_hlist(self) -> #repr {
+                #frunk::generic::Generic::into(self)
+            }
+
+            /// Convert from the HList of all fields, in declaration order
+            pub fn from_hlist(hlist: #repr) -> Self {
+                #frunk::generic::Generic::from(hlist)
+            }
+        }
+
+        #(#selectors)*
+    }
+}
+
+fn impl_size_checks(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> syn::Result<proc_macro2::TokenStream> {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    // generic structs are checked for each of their listed instantiations
+    let instances = if ast.generics.params.is_empty() {
+        vec![quote! { #name }]
+    } else {
+        options
+            .instantiate
+            .iter()
+            .map(|ty| quote! { #ty })
+            .collect()
+    };
+    let field_checks: Vec<_> = fields
+        .iter()
+        .filter_map(|f| {
+            f.options
+                .max_size
+                .as_ref()
+                .map(|max_size| (f.ty.to_token_stream(), max_size))
+        })
+        .collect();
+    if let Some((_, max_size)) = field_checks.first() {
+        if !ast.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                max_size,
+                "Typemap field option `max_size` does not support generic structs!",
+            ));
+        }
+    }
+    if let Some(max_size) = &options.max_size {
+        if instances.is_empty() {
+            return Err(syn::Error::new_spanned(
+                max_size,
+                "Typemap option `max_size` requires `instantiate(...)` on generic structs!",
+            ));
+        }
+    }
+    let checks: Vec<_> = options
+        .max_size
+        .iter()
+        .flat_map(|max_size| instances.iter().map(move |ty| (ty.clone(), max_size)))
+        .chain(field_checks)
+        .collect();
+    // the array lengths only match if the size is within the budget, otherwise
+    // the compiler reports the actual size in the error message
+    let checks = checks.into_iter().map(|(ty, max_size)| {
+        quote_spanned! { max_size.span() =>
+            const _: [(); #max_size] = [(); #krate __private::size_within(::core::mem::size_of::<#ty>(), #max_size)];
+        }
+    });
+    Ok(quote! { #(#checks)* })
+}
+
+fn impl_enum(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    data: &syn::DataEnum,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // each variant holding a value gives access to its type, while it is the active one
+    let (variants, types): (Vec<_>, Vec<_>) = data
+        .variants
+        .iter()
+        .filter_map(|variant| match &variant.fields {
+            Fields::Unit => None,
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Some(Ok((&variant.ident, &fields.unnamed[0].ty)))
+            }
+            fields => Some(Err(syn::Error::new_spanned(
+                fields,
+                format!(
+                    "Typemap variant `{}` must hold a single unnamed field, or none!",
+                    variant.ident
+                ),
+            ))),
+        })
+        .collect::<syn::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    for (i, ty) in types.iter().enumerate() {
+        if let Some(j) = types[..i]
+            .iter()
+            .position(|other| ty::type_name(other) == ty::type_name(ty))
+        {
+            return Err(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "Typemap variants `{}` and `{}` hold the same type `{}`, but all variants must hold distinct types!",
+                    variants[j],
+                    variants[i],
+                    ty::type_name(ty)
+                ),
+            ));
+        }
+    }
+    let gen_try_get_mut = if options.mutable {
+        Some(quote! {
+            #(impl #impl_generics #krate TryGetMut<#types> for #name #ty_generics #where_clause {
+                fn try_get_mut(&mut self) -> Option<&mut #types> {
+                    #[allow(unreachable_patterns)]
+                    match self {
+                        #name::#variants(value) => Some(value),
+                        _ => None,
+                    }
+                }
+            })*
+        })
+    } else {
+        None
+    };
+    Ok(quote! {
+        #(impl #impl_generics #krate TryGet<#types> for #name #ty_generics #where_clause {
+            fn try_get(&self) -> Option<&#types> {
+                #[allow(unreachable_patterns)]
+                match self {
+                    #name::#variants(value) => Some(value),
+                    _ => None,
+                }
+            }
+        })*
+        #gen_try_get_mut
+    })
+}
+
+/// Reject fields having the same type, pointing at both, as their implementations would conflict
+fn check_duplicate_types(fields: &[&Field]) -> syn::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        let name = ty::type_name(field.key_ty());
+        if let Some(first) = fields[..i]
+            .iter()
+            .find(|f| ty::type_name(f.key_ty()) == name)
+        {
+            let mut error = syn::Error::new_spanned(
+                field.ty,
+                format!(
+                    "duplicate typemap type `{}`, all fields must have distinct types",
+                    name
+                ),
+            );
+            error.combine(syn::Error::new_spanned(
+                first.ty,
+                format!("first field of type `{}`", name),
+            ));
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// Reject fields accessed through the same trait as types which are distinct but can become the
+/// same for some instantiation of the generic parameters, which would lead to conflicting
+/// implementations
+///
+/// The check is syntactic, so types written differently, such as through aliases, are deemed
+/// distinct, and are left to the compiler.
+fn check_generic_overlaps(
+    ast: &syn::DeriveInput,
+    keys: &[(&Field, &syn::Type)],
+) -> syn::Result<()> {
+    for (i, (field, key)) in keys.iter().enumerate() {
+        if let Some((other, other_key)) = keys[..i]
+            .iter()
+            .find(|(_, other_key)| ty::may_unify(other_key, key, &ast.generics))
+        {
+            let (first, second) = if other.index.index < field.index.index {
+                ((other, other_key), (field, key))
+            } else {
+                ((field, key), (other, other_key))
+            };
+            return Err(syn::Error::new_spanned(
+                field.ty,
+                format!(
+                    "Typemap fields {} and {} accessed as `{}` and `{}` can have the same type when instantiated, but all fields must be accessed as distinct types!",
+                    first.0.index.index,
+                    second.0.index.index,
+                    ty::type_name(first.1),
+                    ty::type_name(second.1)
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The fields along with the type they are accessed as
+fn field_keys<'a>(fields: &[&'a Field<'a>]) -> Vec<(&'a Field<'a>, &'a syn::Type)> {
+    fields.iter().map(|f| (*f, f.key_ty())).collect()
+}
+
+fn impl_sealed(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    types: &[proc_macro2::TokenStream],
+    indices: &[&syn::Member],
+    mut_types: &[proc_macro2::TokenStream],
+    mut_indices: &[&syn::Member],
+) -> proc_macro2::TokenStream {
+    // Get is implemented once for all types, provided that the PhantomData of the type
+    // implements the lookup trait. As only this crate can implement it for PhantomData,
+    // downstream crates cannot add Get impls as they would overlap with this one.
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let mut blanket_generics = ast.generics.clone();
+    blanket_generics.params.push(parse_quote!(__T));
+    let (blanket_impl_generics, _, _) = blanket_generics.split_for_impl();
+    let base_where_clause = ast.generics.clone().make_where_clause().clone();
+    let mut blanket_where_clause = base_where_clause.clone();
+    blanket_where_clause.predicates.push(parse_quote!(
+        ::core::marker::PhantomData<__T>: #krate __private::SealedGet<Self, Value = __T>
+    ));
+    let gen = quote! {
+        impl #blanket_impl_generics #krate Get<__T> for #name #ty_generics #blanket_where_clause {
+            fn get(&self) -> &__T {
+                <::core::marker::PhantomData<__T> as #krate __private::SealedGet<Self>>::get(self)
+            }
+        }
+        #(impl #impl_generics #krate __private::SealedGet<#name #ty_generics>
+            for ::core::marker::PhantomData<#types> #where_clause {
+            type Value = #types;
+            fn get<'__m>(map: &'__m #name #ty_generics) -> &'__m #types {
+                &map.#indices
+            }
+        })*
+    };
+    if mut_types.is_empty() {
+        return gen;
+    }
+    let mut blanket_mut_where_clause = base_where_clause;
+    blanket_mut_where_clause.predicates.push(parse_quote!(
+        ::core::marker::PhantomData<__T>: #krate __private::SealedGetMut<Self, Value = __T>
+    ));
+    quote! {
+        #gen
+        impl #blanket_impl_generics #krate GetMut<__T> for #name #ty_generics #blanket_mut_where_clause {
+            fn get_mut(&mut self) -> &mut __T {
+                <::core::marker::PhantomData<__T> as #krate __private::SealedGetMut<Self>>::get_mut(self)
+            }
+        }
+        #(impl #impl_generics #krate __private::SealedGetMut<#name #ty_generics>
+            for ::core::marker::PhantomData<#mut_types> #where_clause {
+            fn get_mut<'__m>(map: &'__m mut #name #ty_generics) -> &'__m mut #mut_types {
+                &mut map.#mut_indices
+            }
+        })*
+    }
+}
+
+fn impl_into_ref(ast: &syn::DeriveInput, fields: &[&Field]) -> proc_macro2::TokenStream {
+    // the orphan rules forbid converting into a reference to a generic parameter
+    let fields: Vec<_> = fields
+        .iter()
+        .filter(|f| !ty::is_type_param(f.key_ty(), &ast.generics))
+        .collect();
+    let types: Vec<_> = fields.iter().map(|f| f.key_ty()).collect();
+    let indices: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    let (mut_types, mut_indices): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .filter(|f| f.mutable)
+        .map(|f| (f.key_ty(), &f.member))
+        .unzip();
+    let name = &ast.ident;
+    let (_, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let mut ref_generics = ast.generics.clone();
+    ref_generics.params.insert(0, parse_quote!('__m));
+    let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+    quote! {
+        #(impl #ref_impl_generics ::core::convert::From<&'__m #name #ty_generics> for &'__m #types #where_clause {
+            fn from(map: &'__m #name #ty_generics) -> Self {
+                &map.#indices
+            }
+        })*
+        #(impl #ref_impl_generics ::core::convert::From<&'__m mut #name #ty_generics> for &'__m mut #mut_types #where_clause {
+            fn from(map: &'__m mut #name #ty_generics) -> Self {
+                &mut map.#mut_indices
+            }
+        })*
+    }
+}
+
+fn impl_alias(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    alias: &syn::Ident,
+    types: &[proc_macro2::TokenStream],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let vis = &ast.vis;
+    let generics = &ast.generics;
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let mut blanket_generics = generics.clone();
+    blanket_generics
+        .params
+        .push(parse_quote!(__M: ?Sized #(+ #krate Get<#types>)*));
+    let (blanket_impl_generics, _, _) = blanket_generics.split_for_impl();
+    let doc = format!(
+        "Typemaps containing the types of [`{}`], which implements this trait as well",
+        ast.ident
+    );
+    quote! {
+        #[doc = #doc]
+        #vis trait #alias #generics: #(#krate Get<#types>)+* #where_clause {}
+        impl #blanket_impl_generics #alias #ty_generics for __M #where_clause {}
+    }
+}
+
+fn impl_pin(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[&&Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let types: Vec<_> = fields.iter().map(|f| f.key_ty()).collect();
+    let indices = fields.iter().map(|f| &f.member);
+    // structural pinning requires the struct to be Unpin only if the pinned fields are, and its
+    // Drop implementation not to move them, which is ensured by forbidding one
+    let mut unpin_generics = ast.generics.clone();
+    unpin_generics.params.insert(0, parse_quote!('__pin));
+    let (unpin_impl_generics, _, _) = unpin_generics.split_for_impl();
+    let mut unpin_where_clause = ast.generics.clone().make_where_clause().clone();
+    unpin_where_clause
+        .predicates
+        .extend(types.iter().map(|ty| -> syn::WherePredicate {
+            parse_quote!(#krate __private::PinnedField<'__pin, #ty>: ::core::marker::Unpin)
+        }));
+    quote! {
+        #(impl #impl_generics #krate GetPinMut<#types> for #name #ty_generics #where_clause {
+            fn get_pin_mut(self: ::core::pin::Pin<&mut Self>) -> ::core::pin::Pin<&mut #types> {
+                // SAFETY: the field is structurally pinned, as ensured below
+                unsafe { self.map_unchecked_mut(|map| -> &mut #types { &mut map.#indices }) }
+            }
+        })*
+        impl #unpin_impl_generics ::core::marker::Unpin for #name #ty_generics #unpin_where_clause {}
+        const _: () = {
+            #[allow(dead_code)]
+            trait MustNotImplDrop {}
+            #[allow(drop_bounds)]
+            impl<T: ::core::ops::Drop> MustNotImplDrop for T {}
+            impl #impl_generics MustNotImplDrop for #name #ty_generics #where_clause {}
+        };
+    }
+}
+
+fn impl_flatten(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    field: &Field,
+) -> syn::Result<proc_macro2::TokenStream> {
+    // a blanket impl conditioned on the nested typemap only overlaps the impls of the other
+    // fields if the nested typemap provides their types, which the compiler can rule out
+    if options.sealed {
+        return Err(syn::Error::new_spanned(
+            field.ty,
+            "Typemap option `flatten` cannot be used in a `sealed` typemap!",
+        ));
+    }
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let ty = field.ty;
+    let member = &field.member;
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+    let mut blanket_generics = ast.generics.clone();
+    blanket_generics.params.push(parse_quote!(__T));
+    let (blanket_impl_generics, _, _) = blanket_generics.split_for_impl();
+    let base_where_clause = ast.generics.clone().make_where_clause().clone();
+    let mut where_clause = base_where_clause.clone();
+    where_clause
+        .predicates
+        .push(parse_quote!(#ty: #krate Get<__T>));
+    let gen = quote! {
+        impl #blanket_impl_generics #krate Get<__T> for #name #ty_generics #where_clause {
+            fn get(&self) -> &__T {
+                <#ty as #krate Get<__T>>::get(&self.#member)
+            }
+        }
+    };
+    if !field.mutable {
+        return Ok(gen);
+    }
+    let mut mut_where_clause = base_where_clause;
+    mut_where_clause
+        .predicates
+        .push(parse_quote!(#ty: #krate GetMut<__T>));
+    Ok(quote! {
+        #gen
+        impl #blanket_impl_generics #krate GetMut<__T> for #name #ty_generics #mut_where_clause {
+            fn get_mut(&mut self) -> &mut __T {
+                <#ty as #krate GetMut<__T>>::get_mut(&mut self.#member)
+            }
+        }
+    })
+}
+
+fn impl_mem_usage(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let types: Vec<_> = fields.iter().map(|f| f.ty).collect();
+    let indices: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    let count = types.len();
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // fields not implementing MemUsage are detected using autoref-based specialization
+    quote! {
+        impl #impl_generics #krate MemUsage for #name #ty_generics #where_clause {
+            fn mem_usage(&self) -> usize {
+                self.mem_usage_breakdown()
+                    .iter()
+                    .filter_map(|(_, usage)| *usage)
+                    .sum()
+            }
+        }
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Return the type name and memory usage of every field, `None` if it does not implement `MemUsage`
+            pub fn mem_usage_breakdown(&self) -> [(&'static str, Option<usize>); #count] {
+                use #krate __private::{MemUsageFallback as _, MemUsageViaTrait as _};
+                [#((
+                    ::core::any::type_name::<#types>(),
+                    (&#krate __private::MemUsageProbe(&self.#indices)).mem_usage()
+                )),*]
+            }
+        }
+    }
+}
+
+fn impl_commands(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+    derives: &[syn::Path],
+) -> proc_macro2::TokenStream {
+    let krate = options.crate_prefix();
+    let fields: Vec<_> = fields.iter().filter(|f| !f.options.skip).collect();
+    let types: Vec<_> = fields.iter().map(|f| f.ty).collect();
+    let indices: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    let variants: Vec<_> = fields
+        .iter()
+        .map(|f| quote::format_ident!("Field{}", f.index))
+        .collect();
+    let name = &ast.ident;
+    let vis = &ast.vis;
+    let command = quote::format_ident!("{}Command", name);
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // the generic parameters might only be used by skipped fields, so mark them as used
+    let marker = if generics.params.is_empty() {
+        None
+    } else {
+        Some(quote! {
+            #[doc(hidden)]
+            __Marker(::core::convert::Infallible, ::core::marker::PhantomData<fn() -> #name #ty_generics>),
+        })
+    };
+    let marker_arm = marker.as_ref().map(|_| {
+        quote! { #command::__Marker(never, _) => match never {}, }
+    });
+    let doc = format!("Mutation of a field of [`{}`], one variant per field", name);
+    quote! {
+        #[doc = #doc]
+        #[derive(#(#derives),*)]
+        #vis enum #command #generics #where_clause {
+            #(#variants(#types),)*
+            #marker
+        }
+        #(impl #impl_generics #krate SetCommand<#types> for #command #ty_generics #where_clause {
+            fn set(value: #types) -> Self {
+                #command::#variants(value)
+            }
+        })*
+        impl #impl_generics #krate Commands for #name #ty_generics #where_clause {
+            type Command = #command #ty_generics;
+            fn apply(&mut self, command: Self::Command) {
+                match command {
+                    #(#command::#variants(value) => self.#indices = value,)*
+                    #marker_arm
+                }
+            }
+        }
+    }
+}
+
+fn impl_bytes(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    // fields are laid out in declaration order, so each offset is the sum of the previous sizes,
+    // skipped fields being left out of the layout and restored with their default value
+    let krate = options.crate_prefix();
+    let (skipped, encoded): (Vec<_>, Vec<_>) = fields.iter().partition(|f| f.options.skip);
+    let types: Vec<_> = encoded.iter().map(|f| f.ty).collect();
+    let offsets: Vec<_> = (0..types.len())
+        .map(|i| {
+            let previous = &types[..i];
+            quote! { 0 #(+ <#previous as #krate AsBytes>::SIZE)* }
+        })
+        .collect();
+    let indices: Vec<_> = encoded.iter().map(|f| &f.member).collect();
+    let skipped_indices = skipped.iter().map(|f| &f.member);
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #krate AsBytes for #name #ty_generics #where_clause {
+            const SIZE: usize = 0 #(+ <#types as #krate AsBytes>::SIZE)*;
+            fn to_bytes(&self, bytes: &mut [u8]) {
+                #(#krate AsBytes::to_bytes(&self.#indices, &mut bytes[#offsets..]);)*
+            }
+            fn from_bytes(bytes: &[u8]) -> Self {
+                Self {
+                    #(#indices: <#types as #krate AsBytes>::from_bytes(&bytes[#offsets..]),)*
+                    #(#skipped_indices: ::core::default::Default::default(),)*
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "manifest")]
+fn impl_manifest(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    let entries: Vec<_> = fields
+        .iter()
+        .filter(|f| !f.options.skip)
+        .map(|f| {
+            format!(
+                r#"{{"index":{},"type":"{}","mutable":{},"volatile":{}}}"#,
+                f.index.index,
+                escape_json(&ty::type_name(f.ty)),
+                f.mutable,
+                f.options.volatile
+            )
+        })
+        .collect();
+    let manifest = format!(
+        r#"{{"name":"{}","fields":[{}]}}"#,
+        ast.ident,
+        entries.join(",")
+    );
+    let krate = options.crate_prefix();
+    let name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #krate Manifest for #name #ty_generics #where_clause {
+            const MANIFEST: &'static str = #manifest;
+        }
     }
-    .into()
 }
 
-fn has_mut_attr(attrs: &[Attribute]) -> bool {
-    attrs.iter().any(|attr| attr.path.is_ident("typemap_mut"))
+#[cfg(feature = "manifest")]
+fn escape_json(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
 }
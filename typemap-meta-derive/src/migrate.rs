@@ -0,0 +1,201 @@
+//! The `migrate!` macro, generating conversions between versions of a typemap.
+
+use quote::{format_ident, quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, Ident, Path, Token, Type};
+
+mod kw {
+    syn::custom_keyword!(with);
+}
+
+/// A tuple struct along with the types of its fields
+pub(crate) struct TupleStruct {
+    pub path: Path,
+    pub types: Vec<Type>,
+}
+
+impl Parse for TupleStruct {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let content;
+        parenthesized!(content in input);
+        let types = Punctuated::<Type, Token![,]>::parse_terminated(&content)?;
+        Ok(Self {
+            path,
+            types: types.into_iter().collect(),
+        })
+    }
+}
+
+/// How a type of the source struct is handled if it is not in the target one
+#[allow(clippy::large_enum_variant)]
+enum Clause {
+    /// `convert Source => Target;` or `convert Source => Target with path;`
+    Convert {
+        from: Type,
+        to: Type,
+        with: Option<Path>,
+    },
+    /// `drop Source;`
+    Drop(Type),
+}
+
+impl Parse for Clause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let keyword: Ident = input.parse()?;
+        if keyword == "convert" {
+            let from = input.parse()?;
+            input.parse::<Token![=>]>()?;
+            let to = input.parse()?;
+            let with = if input.peek(kw::with) {
+                input.parse::<kw::with>()?;
+                Some(input.parse()?)
+            } else {
+                None
+            };
+            Ok(Clause::Convert { from, to, with })
+        } else if keyword == "drop" {
+            Ok(Clause::Drop(input.parse()?))
+        } else {
+            Err(syn::Error::new(
+                keyword.span(),
+                "expected `convert Source => Target` or `drop Source`",
+            ))
+        }
+    }
+}
+
+pub(crate) struct Migration {
+    from: TupleStruct,
+    to: TupleStruct,
+    clauses: Vec<Clause>,
+}
+
+impl Parse for Migration {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let from = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let to: TupleStruct = input.parse()?;
+        let mut clauses = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![;]>()?;
+            if input.is_empty() {
+                break;
+            }
+            clauses.push(input.parse()?);
+        }
+
+        // every field of the source can only be used once, by moving, converting or dropping it
+        let mut handled: Vec<&Type> = Vec::new();
+        for clause in &clauses {
+            let ty = match clause {
+                Clause::Convert { from, .. } => from,
+                Clause::Drop(dropped) => dropped,
+            };
+            if to.types.iter().any(|t| same_type(t, ty)) {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "type is moved to the target typemap, it cannot also be converted or dropped",
+                ));
+            }
+            if handled.iter().any(|t| same_type(t, ty)) {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "type is already converted or dropped by a previous clause",
+                ));
+            }
+            handled.push(ty);
+        }
+        Ok(Self { from, to, clauses })
+    }
+}
+
+pub(crate) fn same_type(a: &Type, b: &Type) -> bool {
+    a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+impl Migration {
+    pub fn expand(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let source_fields: Vec<_> = (0..self.from.types.len())
+            .map(|i| format_ident!("source_{}", i))
+            .collect();
+        let source_position = |ty: &Type| self.from.types.iter().position(|t| same_type(t, ty));
+
+        // check that every type of the source is either moved, converted or dropped
+        for ty in &self.from.types {
+            let handled = self.to.types.iter().any(|t| same_type(t, ty))
+                || self.clauses.iter().any(|clause| match clause {
+                    Clause::Convert { from, .. } => same_type(from, ty),
+                    Clause::Drop(dropped) => same_type(dropped, ty),
+                });
+            if !handled {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "type is removed by the migration, add `convert` or `drop` to handle it",
+                ));
+            }
+        }
+        for clause in &self.clauses {
+            let from = match clause {
+                Clause::Convert { from, to, .. } => {
+                    if !self.to.types.iter().any(|t| same_type(t, to)) {
+                        return Err(syn::Error::new_spanned(
+                            to,
+                            "converted type is not in the target typemap",
+                        ));
+                    }
+                    from
+                }
+                Clause::Drop(dropped) => dropped,
+            };
+            if source_position(from).is_none() {
+                return Err(syn::Error::new_spanned(
+                    from,
+                    "type is not in the source typemap",
+                ));
+            }
+        }
+
+        // build every field of the target by moving, converting or defaulting
+        let target_values = self.to.types.iter().map(|ty| {
+            if let Some(i) = source_position(ty) {
+                let field = &source_fields[i];
+                return quote! { #field };
+            }
+            let conversion = self.clauses.iter().find_map(|clause| match clause {
+                Clause::Convert { from, to, with } if same_type(to, ty) => Some((from, with)),
+                _ => None,
+            });
+            match conversion {
+                Some((from, Some(with))) => {
+                    let field = &source_fields[source_position(from).unwrap()];
+                    quote! { #with(#field) }
+                }
+                Some((from, None)) => {
+                    let field = &source_fields[source_position(from).unwrap()];
+                    quote! { <#ty as ::core::convert::From<#from>>::from(#field) }
+                }
+                None => quote! { <#ty as ::core::default::Default>::default() },
+            }
+        });
+
+        let from_path = &self.from.path;
+        let from_types = &self.from.types;
+        let to_path = &self.to.path;
+        let to_types = &self.to.types;
+        Ok(quote! {
+            impl ::core::convert::From<#from_path> for #to_path {
+                #[allow(clippy::unused_unit, unused_variables)]
+                fn from(source: #from_path) -> Self {
+                    let #from_path(#(#source_fields),*) = source;
+                    #(let #source_fields: #from_types = #source_fields;)*
+                    #to_path(#({
+                        let value: #to_types = #target_values;
+                        value
+                    }),*)
+                }
+            }
+        })
+    }
+}
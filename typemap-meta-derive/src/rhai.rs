@@ -0,0 +1,130 @@
+//! Registration of typemaps in the rhai scripting engine.
+//!
+//! Fields are accessed from scripts by indexing the typemap with the name of their type.
+//! Common primitives are converted to and from the integer, float, boolean, character and string
+//! types of rhai, while other types are passed as opaque values.
+
+use crate::attr::ContainerOptions;
+use crate::{ty, Field};
+use quote::quote;
+
+const INTEGERS: &[&str] = &[
+    "i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64", "usize",
+];
+const FLOATS: &[&str] = &["f32", "f64"];
+
+pub(crate) fn impl_rhai(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[&Field],
+) -> syn::Result<proc_macro2::TokenStream> {
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &ast.generics,
+            "Typemap option `rhai` does not support generic structs!",
+        ));
+    }
+    let krate = options.crate_prefix();
+    let rhai = quote! { #krate __private::rhai };
+    let name = &ast.ident;
+    let name_str = name.to_string();
+    let keys: Vec<_> = fields.iter().map(|f| ty::type_name(f.ty)).collect();
+
+    let getters = fields.iter().zip(&keys).map(|(f, key)| {
+        let index = &f.member;
+        if INTEGERS.contains(&key.as_str()) {
+            quote! {
+                <#rhai::INT as ::core::convert::TryFrom<_>>::try_from(map.#index)
+                    .map(#rhai::Dynamic::from_int)
+                    .map_err(|_| #rhai::EvalAltResult::ErrorArithmetic(
+                        "integer overflow".into(),
+                        #rhai::Position::NONE,
+                    ).into())
+            }
+        } else if FLOATS.contains(&key.as_str()) {
+            quote! { Ok(#rhai::Dynamic::from_float(map.#index as #rhai::FLOAT)) }
+        } else {
+            quote! { Ok(#rhai::Dynamic::from(::core::clone::Clone::clone(&map.#index))) }
+        }
+    });
+
+    let gen_setter = if fields.iter().any(|f| f.mutable) {
+        let setters = fields
+            .iter()
+            .zip(&keys)
+            .filter(|(f, _)| f.mutable)
+            .map(|(f, key)| {
+                let index = &f.member;
+                let ty = f.ty;
+                let value = if INTEGERS.contains(&key.as_str()) {
+                    quote! {
+                        <#ty as ::core::convert::TryFrom<#rhai::INT>>::try_from(
+                            value.as_int().map_err(|actual| mismatch(actual))?,
+                        )
+                        .map_err(|_| #rhai::EvalAltResult::ErrorArithmetic(
+                            "integer overflow".into(),
+                            #rhai::Position::NONE,
+                        ))?
+                    }
+                } else if FLOATS.contains(&key.as_str()) {
+                    quote! { value.as_float().map_err(|actual| mismatch(actual))? as #ty }
+                } else {
+                    quote! {{
+                        let actual = value.type_name();
+                        value.try_cast::<#ty>().ok_or_else(|| mismatch(actual))?
+                    }}
+                };
+                quote! {
+                    #key => {
+                        let mismatch = |actual: &str| {
+                            #rhai::EvalAltResult::ErrorMismatchDataType(
+                                #key.into(),
+                                actual.into(),
+                                #rhai::Position::NONE,
+                            )
+                        };
+                        map.#index = #value;
+                        Ok(())
+                    }
+                }
+            });
+        Some(quote! {
+            engine.register_indexer_set(
+                |map: &mut #name, key: &str, value: #rhai::Dynamic|
+                    -> #krate __private::RhaiResult<()> {
+                    match key {
+                        #(#setters)*
+                        _ => Err(#rhai::EvalAltResult::ErrorIndexNotFound(
+                            key.into(),
+                            #rhai::Position::NONE,
+                        ).into()),
+                    }
+                },
+            );
+        })
+    } else {
+        None
+    };
+
+    Ok(quote! {
+        impl #name {
+            /// Register this typemap in `engine`, allowing scripts to access its fields by type name
+            pub fn register_rhai(engine: &mut #rhai::Engine) {
+                engine.register_type_with_name::<#name>(#name_str);
+                engine.register_indexer_get(
+                    |map: &mut #name, key: &str|
+                        -> #krate __private::RhaiResult<#rhai::Dynamic> {
+                        match key {
+                            #(#keys => #getters,)*
+                            _ => Err(#rhai::EvalAltResult::ErrorIndexNotFound(
+                                key.into(),
+                                #rhai::Position::NONE,
+                            ).into()),
+                        }
+                    },
+                );
+                #gen_setter
+            }
+        }
+    })
+}
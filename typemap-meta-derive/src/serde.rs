@@ -0,0 +1,205 @@
+//! Implementation of serde's `Serialize` and `Deserialize` for typemaps.
+//!
+//! Typemaps are serialized as structs whose field names are the type names of the fields,
+//! unless renamed.
+
+use crate::attr::ContainerOptions;
+use crate::{ty, Field};
+use quote::{format_ident, quote};
+
+pub(crate) fn impl_serde(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    fields: &[Field],
+) -> syn::Result<proc_macro2::TokenStream> {
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &ast.generics,
+            "Typemap option `serde` does not support generic structs!",
+        ));
+    }
+    let krate = options.crate_prefix();
+    let serde = quote! { #krate __private::serde };
+    let name = &ast.ident;
+    let name_str = name.to_string();
+    let serialized: Vec<_> = fields.iter().filter(|f| !f.options.serde.skip).collect();
+    let keys: Vec<_> = serialized
+        .iter()
+        .map(|f| {
+            f.options
+                .serde
+                .rename
+                .clone()
+                .unwrap_or_else(|| ty::type_name(f.ty))
+        })
+        .collect();
+    let count = serialized.len();
+
+    // fields serialized with a custom module go through wrappers calling its functions
+    let serialize_values = serialized.iter().map(|f| {
+        let index = &f.member;
+        let ty = f.ty;
+        match &f.options.serde.with {
+            Some(with) => quote! {{
+                struct SerializeWith<'a>(&'a #ty);
+                impl #serde::Serialize for SerializeWith<'_> {
+                    fn serialize<S: #serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                        #with::serialize(self.0, serializer)
+                    }
+                }
+                &SerializeWith(&self.#index)
+            }},
+            None => quote! { &self.#index },
+        }
+    });
+    let mut deserialize_wrappers = Vec::new();
+    let deserialize_values: Vec<_> = serialized
+        .iter()
+        .map(|f| {
+            let ty = f.ty;
+            match &f.options.serde.with {
+                Some(with) => {
+                    let wrapper = format_ident!("DeserializeWith{}", f.index);
+                    deserialize_wrappers.push(quote! {
+                        struct #wrapper(#ty);
+                        impl<'de> #serde::Deserialize<'de> for #wrapper {
+                            fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                                #with::deserialize(deserializer).map(#wrapper)
+                            }
+                        }
+                    });
+                    quote! { #wrapper }
+                }
+                None => quote! { #ty },
+            }
+        })
+        .collect();
+    let unwrap_values: Vec<_> = serialized
+        .iter()
+        .map(|f| f.options.serde.with.as_ref().map(|_| quote! { .0 }))
+        .collect();
+    // like with serde's derive, missing optional fields are `None`, unless deserialized with a
+    // custom module
+    let defaulted = |f: &Field| {
+        f.options.serde.default
+            || (f.options.serde.with.is_none() && ty::wrapped_type(f.ty, "Option").is_some())
+    };
+    let missing_keys = serialized.iter().zip(&keys).map(|(f, key)| {
+        if defaulted(f) {
+            quote! { .unwrap_or_default() }
+        } else {
+            quote! { .ok_or_else(|| Error::missing_field(#key))? }
+        }
+    });
+    let missing_elements = serialized.iter().enumerate().map(|(position, f)| {
+        if f.options.serde.default {
+            quote! { .unwrap_or_default() }
+        } else {
+            quote! { .ok_or_else(|| Error::invalid_length(#position, &self))? }
+        }
+    });
+    let map_unwrap_values = serialized.iter().map(|f| {
+        f.options
+            .serde
+            .with
+            .as_ref()
+            .map(|_| quote! { .map(|value| value.0) })
+    });
+
+    let variables: Vec<_> = serialized
+        .iter()
+        .map(|f| format_ident!("field_{}", f.index))
+        .collect();
+    let field_ids: Vec<_> = serialized
+        .iter()
+        .map(|f| format_ident!("Field{}", f.index))
+        .collect();
+    let constructor_values = fields.iter().map(|f| {
+        if f.options.serde.skip {
+            quote! { ::core::default::Default::default() }
+        } else {
+            let variable = format_ident!("field_{}", f.index);
+            quote! { #variable }
+        }
+    });
+    let constructor_values: Vec<_> = constructor_values.collect();
+    let members: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    let expecting = format!("typemap {}", name);
+
+    Ok(quote! {
+        impl #serde::Serialize for #name {
+            fn serialize<S: #serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use #serde::ser::SerializeStruct;
+                let mut state = serializer.serialize_struct(#name_str, #count)?;
+                #(state.serialize_field(#keys, #serialize_values)?;)*
+                state.end()
+            }
+        }
+
+        impl<'de> #serde::Deserialize<'de> for #name {
+            fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                use #serde::de::{Error, MapAccess, SeqAccess};
+                const FIELDS: &[&str] = &[#(#keys),*];
+                #(#deserialize_wrappers)*
+
+                enum FieldId {
+                    #(#field_ids,)*
+                    Unknown,
+                }
+                impl<'de> #serde::Deserialize<'de> for FieldId {
+                    fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                        struct FieldIdVisitor;
+                        impl<'de> #serde::de::Visitor<'de> for FieldIdVisitor {
+                            type Value = FieldId;
+                            fn expecting(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                                f.write_str("a field name")
+                            }
+                            fn visit_str<E: Error>(self, value: &str) -> Result<FieldId, E> {
+                                Ok(match value {
+                                    #(#keys => FieldId::#field_ids,)*
+                                    _ => FieldId::Unknown,
+                                })
+                            }
+                        }
+                        deserializer.deserialize_identifier(FieldIdVisitor)
+                    }
+                }
+
+                struct Visitor;
+                impl<'de> #serde::de::Visitor<'de> for Visitor {
+                    type Value = #name;
+                    fn expecting(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+                    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<#name, A::Error> {
+                        #(let #variables = seq
+                            .next_element::<#deserialize_values>()?
+                            #map_unwrap_values
+                            #missing_elements;)*
+                        Ok(#name { #(#members: #constructor_values),* })
+                    }
+                    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<#name, A::Error> {
+                        #(let mut #variables = None;)*
+                        while let Some(id) = map.next_key::<FieldId>()? {
+                            match id {
+                                #(FieldId::#field_ids => {
+                                    if #variables.is_some() {
+                                        return Err(Error::duplicate_field(#keys));
+                                    }
+                                    #variables = Some(map.next_value::<#deserialize_values>()? #unwrap_values);
+                                })*
+                                FieldId::Unknown => {
+                                    map.next_value::<#serde::de::IgnoredAny>()?;
+                                }
+                            }
+                        }
+                        #(let #variables = #variables #missing_keys;)*
+                        Ok(#name { #(#members: #constructor_values),* })
+                    }
+                }
+
+                deserializer.deserialize_struct(#name_str, FIELDS, Visitor)
+            }
+        }
+    })
+}
@@ -0,0 +1,268 @@
+//! Syntactic analysis of field types.
+
+use quote::ToTokens;
+use syn::{Expr, GenericArgument, Generics, PathArguments, Type};
+
+/// If `ty` is `Wrapper<T>` (possibly with a path prefix), return `T`
+pub(crate) fn wrapped_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => args,
+        _ => return None,
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// If `ty` is `Vec<T>` or `heapless::Vec<T, N>`, return `T` and whether the capacity is bounded
+///
+/// Bounded vectors must be written with their `heapless` path, as the second argument of other
+/// vectors, such as the allocator of `alloc::vec::Vec<T, A>`, is not a capacity.
+pub(crate) fn vec_element(ty: &Type) -> Option<(&Type, bool)> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Vec" {
+        return None;
+    }
+    let bounded = path
+        .segments
+        .iter()
+        .any(|segment| segment.ident == "heapless");
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 + bounded as usize => args,
+        _ => return None,
+    };
+    match args.args.first()? {
+        GenericArgument::Type(element) => Some((element, bounded)),
+        _ => None,
+    }
+}
+
+/// If `ty` is one of the atomic types of `core::sync::atomic` (possibly with a path prefix),
+/// return the type of its value
+pub(crate) fn atomic_value_type(ty: &Type) -> Option<Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    let value = match segment.ident.to_string().as_str() {
+        "AtomicBool" => "bool",
+        "AtomicI8" => "i8",
+        "AtomicI16" => "i16",
+        "AtomicI32" => "i32",
+        "AtomicI64" => "i64",
+        "AtomicIsize" => "isize",
+        "AtomicU8" => "u8",
+        "AtomicU16" => "u16",
+        "AtomicU32" => "u32",
+        "AtomicU64" => "u64",
+        "AtomicUsize" => "usize",
+        _ => return None,
+    };
+    syn::parse_str(value).ok()
+}
+
+/// Human-readable name of a type, as written in the source but with normalized spacing
+pub(crate) fn type_name(ty: &syn::Type) -> String {
+    // token streams are printed with spaces between all tokens, only keep those separating words
+    let spaced = ty.to_token_stream().to_string();
+    let chars: Vec<_> = spaced.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| {
+            c != ' '
+                || (i > 0 && i + 1 < chars.len() && is_word(chars[i - 1]) && is_word(chars[i + 1]))
+        })
+        .map(|(_, &c)| c)
+        .collect()
+}
+
+/// Snake case version of a camel case name, like that of a type
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// If `ty` is `embassy_sync::mutex::Mutex<M, T>`, return `M` and `T`
+///
+/// Mutexes must be written with their `embassy_sync` path unless `explicit`, as other mutexes,
+/// such as `lock_api::Mutex<R, T>`, also have two arguments.
+#[cfg(feature = "embassy")]
+pub(crate) fn mutex_types(ty: &Type, explicit: bool) -> Option<(&Type, &Type)> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    let embassy = explicit
+        || path
+            .segments
+            .iter()
+            .any(|segment| segment.ident == "embassy_sync");
+    if !embassy || segment.ident != "Mutex" {
+        return None;
+    }
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 2 => args,
+        _ => return None,
+    };
+    match (&args.args[0], &args.args[1]) {
+        (GenericArgument::Type(raw), GenericArgument::Type(value)) => Some((raw, value)),
+        _ => None,
+    }
+}
+
+/// Whether `ty` is one of the type parameters of `generics`
+pub(crate) fn is_type_param(ty: &Type, generics: &Generics) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => generics
+            .type_params()
+            .any(|param| path.path.is_ident(&param.ident)),
+        _ => false,
+    }
+}
+
+/// Whether some instantiation of the parameters of `generics` can make `a` and `b` the same type,
+/// lifetimes being ignored as they do not distinguish implementations
+pub(crate) fn may_unify(a: &Type, b: &Type, generics: &Generics) -> bool {
+    // a parameter cannot be a type strictly containing it, such as `[T; 1]` for `T`
+    if is_type_param(a, generics) {
+        return is_type_param(b, generics) || !contains(b, a);
+    }
+    if is_type_param(b, generics) {
+        return !contains(a, b);
+    }
+    match (a, b) {
+        (Type::Group(a), b) | (b, Type::Group(a)) => may_unify(&a.elem, b, generics),
+        (Type::Paren(a), b) | (b, Type::Paren(a)) => may_unify(&a.elem, b, generics),
+        (Type::Path(a), Type::Path(b)) if a.qself.is_none() && b.qself.is_none() => {
+            a.path.leading_colon.is_some() == b.path.leading_colon.is_some()
+                && a.path.segments.len() == b.path.segments.len()
+                && a.path.segments.iter().zip(&b.path.segments).all(|(a, b)| {
+                    a.ident == b.ident
+                        && match (&a.arguments, &b.arguments) {
+                            (PathArguments::None, PathArguments::None) => true,
+                            (
+                                PathArguments::AngleBracketed(a),
+                                PathArguments::AngleBracketed(b),
+                            ) => {
+                                a.args.len() == b.args.len()
+                                    && a.args
+                                        .iter()
+                                        .zip(&b.args)
+                                        .all(|(a, b)| arguments_may_unify(a, b, generics))
+                            }
+                            (a, b) => {
+                                a.to_token_stream().to_string() == b.to_token_stream().to_string()
+                            }
+                        }
+                })
+        }
+        (Type::Reference(a), Type::Reference(b)) => {
+            a.mutability.is_some() == b.mutability.is_some()
+                && may_unify(&a.elem, &b.elem, generics)
+        }
+        (Type::Ptr(a), Type::Ptr(b)) => {
+            a.mutability.is_some() == b.mutability.is_some()
+                && may_unify(&a.elem, &b.elem, generics)
+        }
+        (Type::Slice(a), Type::Slice(b)) => may_unify(&a.elem, &b.elem, generics),
+        (Type::Array(a), Type::Array(b)) => {
+            may_unify(&a.elem, &b.elem, generics) && consts_may_unify(&a.len, &b.len, generics)
+        }
+        (Type::Tuple(a), Type::Tuple(b)) => {
+            a.elems.len() == b.elems.len()
+                && a.elems
+                    .iter()
+                    .zip(&b.elems)
+                    .all(|(a, b)| may_unify(a, b, generics))
+        }
+        (a, b) => type_name(a) == type_name(b),
+    }
+}
+
+/// Whether `ty` contains `param` in one of its components, at any depth
+fn contains(ty: &Type, param: &Type) -> bool {
+    let components: Vec<&Type> = match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .iter()
+            .flat_map(|segment| match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().collect(),
+                _ => Vec::new(),
+            })
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(arg) => Some(arg),
+                _ => None,
+            })
+            .collect(),
+        Type::Group(group) => vec![&group.elem],
+        Type::Paren(paren) => vec![&paren.elem],
+        Type::Reference(reference) => vec![&reference.elem],
+        Type::Ptr(ptr) => vec![&ptr.elem],
+        Type::Slice(slice) => vec![&slice.elem],
+        Type::Array(array) => vec![&array.elem],
+        Type::Tuple(tuple) => tuple.elems.iter().collect(),
+        _ => Vec::new(),
+    };
+    components
+        .into_iter()
+        .any(|component| type_name(component) == type_name(param) || contains(component, param))
+}
+
+fn arguments_may_unify(a: &GenericArgument, b: &GenericArgument, generics: &Generics) -> bool {
+    match (a, b) {
+        (GenericArgument::Lifetime(_), GenericArgument::Lifetime(_)) => true,
+        (GenericArgument::Type(a), GenericArgument::Type(b)) => may_unify(a, b, generics),
+        (GenericArgument::Const(a), GenericArgument::Const(b)) => consts_may_unify(a, b, generics),
+        // a const parameter given as argument is parsed as a type
+        (GenericArgument::Type(ty), GenericArgument::Const(_))
+        | (GenericArgument::Const(_), GenericArgument::Type(ty)) => is_const_param(ty, generics),
+        (a, b) => a.to_token_stream().to_string() == b.to_token_stream().to_string(),
+    }
+}
+
+fn consts_may_unify(a: &Expr, b: &Expr, generics: &Generics) -> bool {
+    let is_param = |expr: &Expr| match expr {
+        Expr::Path(path) => generics
+            .const_params()
+            .any(|param| path.path.is_ident(&param.ident)),
+        _ => false,
+    };
+    is_param(a) || is_param(b) || a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+fn is_const_param(ty: &Type, generics: &Generics) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => generics
+            .const_params()
+            .any(|param| path.path.is_ident(&param.ident)),
+        _ => false,
+    }
+}
@@ -0,0 +1,61 @@
+//! Facade over static and runtime typemaps.
+
+use core::any::{Any, TypeId};
+
+/// Lookup of values by type, implemented both by static typemaps and by runtime ones
+///
+/// Static typemaps implement it with `#[typemap(any)]`, which requires all their fields to be
+/// `'static`. With the `type-map` feature, it is also implemented for `type_map::TypeMap`.
+/// Code written against this trait hence accepts either kind of typemap, at the price of
+/// the lookup being checked at run time.
+pub trait AnyTypemap {
+    /// Return a reference to the value of type `T`, if any
+    fn lookup<T: Any>(&self) -> Option<&T>;
+
+    /// Return a mutable reference to the value of type `T`, if any
+    ///
+    /// Static typemaps only return mutable references if they have `#[typemap(mut)]`.
+    fn lookup_mut<T: Any>(&mut self) -> Option<&mut T>;
+}
+
+#[cfg(feature = "type-map")]
+impl AnyTypemap for type_map::TypeMap {
+    fn lookup<T: Any>(&self) -> Option<&T> {
+        self.get()
+    }
+
+    fn lookup_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.get_mut()
+    }
+}
+
+/// Object-safe interface of static typemaps, allowing to handle different ones as `&dyn DynTypemap`
+///
+/// It is implemented by the derive macro with `#[typemap(any)]`, considering the fields accessible
+/// by reference. `dyn DynTypemap` implements [`AnyTypemap`], so that values can be looked up by
+/// type through it.
+pub trait DynTypemap {
+    /// Return a reference to the field whose type has identifier `id`, if any
+    fn get_dyn(&self, id: TypeId) -> Option<&dyn Any>;
+
+    /// Return a mutable reference to the field whose type has identifier `id`, if any
+    ///
+    /// Only fields that are mutable in the typemap are returned.
+    fn get_dyn_mut(&mut self, id: TypeId) -> Option<&mut dyn Any>;
+
+    /// Number of fields
+    fn field_count(&self) -> usize;
+
+    /// Name of the type of the field at `index`, as given by [`core::any::type_name`], if any
+    fn type_name(&self, index: usize) -> Option<&'static str>;
+}
+
+impl AnyTypemap for dyn DynTypemap + '_ {
+    fn lookup<T: Any>(&self) -> Option<&T> {
+        self.get_dyn(TypeId::of::<T>())?.downcast_ref()
+    }
+
+    fn lookup_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.get_dyn_mut(TypeId::of::<T>())?.downcast_mut()
+    }
+}
@@ -0,0 +1,12 @@
+//! Atomic fields, accessed by the type of their value.
+
+use core::sync::atomic::Ordering;
+
+/// Helper trait to atomically access a specific type `T` from a tuple struct containing an atomic of it
+///
+/// It is implemented by the derive macro for fields marked with `#[typemap(atomic)]`, whose
+/// type is one of the atomic types of [`core::sync::atomic`], such as `AtomicU32` for `u32`.
+pub trait AtomicGet<T> {
+    fn load(&self, order: Ordering) -> T;
+    fn store(&self, value: T, order: Ordering);
+}
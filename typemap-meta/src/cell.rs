@@ -0,0 +1,17 @@
+//! Fields wrapped in cells, accessed by the type of their value.
+
+/// Helper trait to access a specific type `T` through a shared reference to a tuple struct containing a `Cell` or a `RefCell` of it
+///
+/// It is implemented by the derive macro for the fields of the `{Name}Cell` companion struct
+/// generated with `#[typemap(cell)]`, which also has inherent `get`, `set` and `replace`
+/// methods taking the type as a generic parameter.
+pub trait CellGet<T> {
+    /// Get a copy of the value, panicking if a `RefCell` is mutably borrowed
+    fn get(&self) -> T
+    where
+        T: Copy;
+    /// Set the value, panicking if a `RefCell` is borrowed
+    fn set(&self, value: T);
+    /// Replace the value, returning the previous one, panicking if a `RefCell` is borrowed
+    fn replace(&self, value: T) -> T;
+}
@@ -0,0 +1,71 @@
+//! Fixed-size binary encoding, usable without allocation.
+
+/// Fixed-size binary encoding with a deterministic little-endian layout
+///
+/// It is implemented for the primitive types and arrays of them, and can be derived
+/// for typemaps using `#[typemap_bytes]`, in which case the fields are laid out in
+/// declaration order.
+pub trait AsBytes: Sized {
+    /// Number of bytes used by the encoding
+    const SIZE: usize;
+
+    /// Encode `self` into the first [`SIZE`](AsBytes::SIZE) bytes of `bytes`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is shorter than [`SIZE`](AsBytes::SIZE).
+    fn to_bytes(&self, bytes: &mut [u8]);
+
+    /// Decode a value from the first [`SIZE`](AsBytes::SIZE) bytes of `bytes`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is shorter than [`SIZE`](AsBytes::SIZE).
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_as_bytes {
+    ($($t:ty),*) => {
+        $(impl AsBytes for $t {
+            const SIZE: usize = core::mem::size_of::<$t>();
+            fn to_bytes(&self, bytes: &mut [u8]) {
+                bytes[..Self::SIZE].copy_from_slice(&self.to_le_bytes());
+            }
+            fn from_bytes(bytes: &[u8]) -> Self {
+                let mut buffer = [0; core::mem::size_of::<$t>()];
+                buffer.copy_from_slice(&bytes[..Self::SIZE]);
+                <$t>::from_le_bytes(buffer)
+            }
+        })*
+    };
+}
+
+impl_as_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl AsBytes for bool {
+    const SIZE: usize = 1;
+    fn to_bytes(&self, bytes: &mut [u8]) {
+        bytes[0] = *self as u8;
+    }
+    fn from_bytes(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+impl AsBytes for () {
+    const SIZE: usize = 0;
+    fn to_bytes(&self, _bytes: &mut [u8]) {}
+    fn from_bytes(_bytes: &[u8]) -> Self {}
+}
+
+impl<T: AsBytes, const N: usize> AsBytes for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+    fn to_bytes(&self, bytes: &mut [u8]) {
+        for (i, value) in self.iter().enumerate() {
+            value.to_bytes(&mut bytes[i * T::SIZE..]);
+        }
+    }
+    fn from_bytes(bytes: &[u8]) -> Self {
+        core::array::from_fn(|i| T::from_bytes(&bytes[i * T::SIZE..]))
+    }
+}
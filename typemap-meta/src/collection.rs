@@ -0,0 +1,19 @@
+//! Access to collection-valued fields, keyed by the type of their elements.
+
+/// Helper trait to get all values of a specific type `T` from a tuple struct containing a collection of them
+///
+/// It is implemented by the derive macro for fields of type `Vec<T>` or `heapless::Vec<T, N>`.
+pub trait GetAll<T> {
+    fn get_all(&self) -> &[T];
+}
+
+/// Helper trait to mutably get all values of a specific type `T` from a tuple struct containing a collection of them
+pub trait GetAllMut<T>: GetAll<T> {
+    fn get_all_mut(&mut self) -> &mut [T];
+}
+
+/// Helper trait to add a value of a specific type `T` to a tuple struct containing a collection of them
+pub trait Push<T>: GetAll<T> {
+    /// Add `value` to the collection, or return it if the collection is full
+    fn push(&mut self, value: T) -> Result<(), T>;
+}
@@ -0,0 +1,61 @@
+//! Recording of typemap mutations as typed commands, for inspection and replay.
+
+use crate::{Get, GetMut};
+
+/// Typemap whose mutations can be represented as commands, implemented with `#[typemap(commands)]`
+pub trait Commands {
+    /// Enumeration of the possible mutations, one variant per field setting its value
+    type Command;
+
+    /// Apply `command` to this typemap, replacing the value of the corresponding field
+    fn apply(&mut self, command: Self::Command);
+}
+
+/// Construction of the command setting the field of type `T`
+pub trait SetCommand<T> {
+    fn set(value: T) -> Self;
+}
+
+/// Handle mutating a typemap `M` while passing all the mutations, as commands, to a sink
+///
+/// The recorded commands can be replayed onto another instance using [`Commands::apply`].
+pub struct Recorder<'a, M: Commands, F: FnMut(&M::Command)> {
+    map: &'a mut M,
+    sink: F,
+}
+
+impl<'a, M: Commands, F: FnMut(&M::Command)> Recorder<'a, M, F> {
+    pub fn new(map: &'a mut M, sink: F) -> Self {
+        Self { map, sink }
+    }
+
+    /// Set the field of type `T` to `value`, recording the command
+    pub fn set<T>(&mut self, value: T)
+    where
+        M::Command: SetCommand<T>,
+    {
+        self.record(M::Command::set(value));
+    }
+
+    /// Mutate the field of type `T` using `f`, recording a command setting its resulting value
+    pub fn update<T: Clone>(&mut self, f: impl FnOnce(&mut T))
+    where
+        M: Get<T> + GetMut<T>,
+        M::Command: SetCommand<T>,
+    {
+        f(self.map.get_mut());
+        (self.sink)(&M::Command::set(self.map.get().clone()));
+    }
+
+    /// Apply `command`, recording it
+    pub fn record(&mut self, command: M::Command) {
+        (self.sink)(&command);
+        self.map.apply(command);
+    }
+}
+
+impl<T, M: Commands + Get<T>, F: FnMut(&M::Command)> Get<T> for Recorder<'_, M, F> {
+    fn get(&self) -> &T {
+        self.map.get()
+    }
+}
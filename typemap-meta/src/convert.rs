@@ -0,0 +1,30 @@
+//! Conversions between typemaps, matching their fields by type.
+//!
+//! The derive macro implements [`FromFields`] on all structs, building them from the fields of
+//! another typemap, and with `#[typemap(into(Target))]`, [`IntoFields`] and [`TakeField`] on the
+//! source typemap as well as `From<Source>` for the target. The source field converted into
+//! each target field is found by the compiler, as the only one whose type converts with
+//! [`From`] into the type of the target field, which is checked to be a different one for
+//! every target field when the conversion is compiled.
+
+/// Index of a field of a typemap, identifying the source field of a conversion
+pub struct FieldIndex<const I: usize>;
+
+/// Helper trait to move the fields out of a typemap, to convert them into another typemap
+pub trait IntoFields {
+    /// Fields of the typemap, each one held until it is taken
+    type Fields;
+
+    fn into_fields(self) -> Self::Fields;
+}
+
+/// Helper trait to take the field identified by `I` out of the fields of a typemap, converting it into `U`
+pub trait TakeField<U, I>: IntoFields {
+    /// Take the field, panicking if it has already been taken
+    fn take_field(fields: &mut Self::Fields) -> U;
+}
+
+/// Helper trait to build a typemap from the fields of typemap `M`, `I` identifying the field of `M` taken for each of its own fields
+pub trait FromFields<M, I>: Sized {
+    fn from_fields(map: M) -> Self;
+}
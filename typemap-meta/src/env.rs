@@ -0,0 +1,74 @@
+//! Construction of typemaps from environment variables.
+
+extern crate alloc;
+extern crate std;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Problem with an environment variable read by a `from_env` constructor
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EnvVarError {
+    /// The variable is not set, or is not valid unicode
+    Missing(&'static str),
+    /// The variable could not be parsed, along with the parsing error
+    Invalid(&'static str, String),
+}
+
+impl fmt::Display for EnvVarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvVarError::Missing(name) => write!(f, "environment variable `{}` is missing", name),
+            EnvVarError::Invalid(name, error) => {
+                write!(f, "environment variable `{}` is invalid: {}", name, error)
+            }
+        }
+    }
+}
+
+/// All problems with the environment variables read by a `from_env` constructor
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvError(pub Vec<EnvVarError>);
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            error.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+/// Value of the environment variable `name`, if it is set and valid unicode
+pub fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Look up and parse the variable `name`, recording any problem in `errors`
+pub fn env_field<T, E: fmt::Display, S: AsRef<str>>(
+    errors: &mut EnvError,
+    lookup: &mut impl FnMut(&str) -> Option<S>,
+    name: &'static str,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Option<T> {
+    let value = match lookup(name) {
+        Some(value) => value,
+        None => {
+            errors.0.push(EnvVarError::Missing(name));
+            return None;
+        }
+    };
+    match parse(value.as_ref()) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            errors.0.push(EnvVarError::Invalid(name, error.to_string()));
+            None
+        }
+    }
+}
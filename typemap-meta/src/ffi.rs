@@ -0,0 +1,40 @@
+//! Stable layouts, for sharing typemaps across FFI or shared-memory boundaries.
+
+use crate::CachePadded;
+
+/// Marker of the types having a stable layout that can be shared across an FFI boundary
+///
+/// It is implemented for the primitive types, raw pointers, arrays and [`CachePadded`] of
+/// them, and by the derive macro for typemaps with `#[typemap(ffi)]`.
+///
+/// # Safety
+///
+/// Implementors must have a layout defined by the C ABI, for instance through `#[repr(C)]`,
+/// `#[repr(transparent)]` or a primitive `#[repr]`, and all their fields must be `FfiSafe`.
+pub unsafe trait FfiSafe {}
+
+macro_rules! impl_ffi_safe {
+    ($($t:ty),*) => {
+        $(unsafe impl FfiSafe for $t {})*
+    };
+}
+
+impl_ffi_safe!(u8, u16, u32, u64, i8, i16, i32, i64, usize, isize, f32, f64, bool);
+
+unsafe impl<T: FfiSafe, const N: usize> FfiSafe for [T; N] {}
+unsafe impl<T> FfiSafe for *const T {}
+unsafe impl<T> FfiSafe for *mut T {}
+unsafe impl<T: FfiSafe> FfiSafe for CachePadded<T> {}
+
+/// Layout of a field of a typemap, as provided by the `LAYOUT` constant of typemaps with `#[typemap(ffi)]`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// Name of the type of the field, as written in the source
+    pub name: &'static str,
+    /// Offset of the field from the start of the typemap, in bytes
+    pub offset: usize,
+    /// Size of the field, in bytes
+    pub size: usize,
+    /// Alignment of the field, in bytes
+    pub align: usize,
+}
@@ -0,0 +1,36 @@
+//! Immutable typemaps cheaply shared after being built.
+
+extern crate alloc;
+
+use crate::Get;
+use alloc::sync::Arc;
+
+/// Typemap frozen after being built, cheaply cloneable and only implementing [`Get`]
+///
+/// It is obtained with the `freeze` method generated by `#[typemap(freeze)]`, and can be turned
+/// back into the owned typemap with [`thaw`](Frozen::thaw) once no other clone remains.
+pub struct Frozen<M>(Arc<M>);
+
+impl<M> Frozen<M> {
+    /// Freeze `map`
+    pub fn new(map: M) -> Self {
+        Self(Arc::new(map))
+    }
+
+    /// Get back the owned typemap if `self` is its only clone, or `self` otherwise
+    pub fn thaw(self) -> Result<M, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+
+impl<M> Clone for Frozen<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T, M: Get<T>> Get<T> for Frozen<M> {
+    fn get(&self) -> &T {
+        self.0.get()
+    }
+}
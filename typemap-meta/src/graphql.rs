@@ -0,0 +1,38 @@
+//! Access to typemaps installed as async-graphql context data.
+
+use crate::Get;
+use async_graphql::context::ContextBase;
+
+/// Extension of the async-graphql context, accessing a typemap installed as context data
+///
+/// The typemap is installed once with `SchemaBuilder::data`, and resolvers generic over the
+/// typemap then access its values with compile-time checks, instead of looking up each type
+/// in the context data at run time.
+pub trait TypemapContext {
+    /// Get the typemap `M` installed as context data
+    ///
+    /// # Panics
+    ///
+    /// Panics if no typemap of type `M` was installed.
+    fn typemap<M: Send + Sync + 'static>(&self) -> &M;
+
+    /// Get the value of type `T` of the typemap `M` installed as context data
+    ///
+    /// # Panics
+    ///
+    /// Panics if no typemap of type `M` was installed.
+    fn get_typed<M: Get<T> + Send + Sync + 'static, T>(&self) -> &T {
+        self.typemap::<M>().get()
+    }
+}
+
+impl<T> TypemapContext for ContextBase<'_, T> {
+    fn typemap<M: Send + Sync + 'static>(&self) -> &M {
+        self.data_opt::<M>().unwrap_or_else(|| {
+            panic!(
+                "typemap `{}` is not installed as context data",
+                core::any::type_name::<M>()
+            )
+        })
+    }
+}
@@ -10,7 +10,8 @@
 //! The crate is `no_std` compatible.
 //!
 //! # Example
-//! ```
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
 //! # use typemap_meta::*;
 //! #[derive(Typemap)]
 //! struct Test(i32, f32);
@@ -20,9 +21,45 @@
 //! assert_eq!(*get!(t, f32), 2.0);
 //! ```
 //!
+//! The generated code refers to the items of this crate by their absolute path, so they do not
+//! need to be in scope, and cannot be confused with items of the same name:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! trait Get {}
+//!
+//! #[derive(typemap_meta::Typemap)]
+//! struct Test(i32, f32);
+//!
+//! let t = Test(1, 2.0);
+//! assert_eq!(*typemap_meta::get!(t, i32), 1);
+//! ```
+//!
+//! Crates re-exporting this one, whose users might not depend on it directly, can set the path
+//! used by the generated code with `#[typemap(crate = "path")]`, also accepted by [`typemap!`]
+//! and [`difference!`], or with a `crate = "path"` argument of [`borrow_map!`] and
+//! [`typemap_fields`]:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! mod framework {
+//!     pub mod typemap {
+//!         pub use typemap_meta::*;
+//!     }
+//! }
+//!
+//! #[derive(framework::typemap::Typemap)]
+//! #[typemap(crate = "framework::typemap")]
+//! struct Test(i32, f32);
+//!
+//! let t = Test(1, 2.0);
+//! assert_eq!(*framework::typemap::get!(t, f32), 2.0);
+//! let b = framework::typemap::borrow_map!(crate = "framework::typemap", &t.0: i32);
+//! assert_eq!(*framework::typemap::get!(b, i32), 1);
+//! ```
+//!
 //! To get mutable references, add the `#[typemap_mut]` attribute on your struct, and
 //! use `get_mut!` instead of `get!`:
-//! ```
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
 //! # use typemap_meta::*;
 //! #[derive(Typemap)]
 //! #[typemap_mut]
@@ -38,458 +75,4611 @@
 //! assert_eq!(*get!(t, i32), 3);
 //! assert_eq!(*get!(t, f32), 4.0);
 //! ```
+//!
+//! Mutability is chosen per struct, and `#[typemap_readonly]` opts a field of a mutable struct
+//! out of the mutable references. On a struct, it states explicitly that it is immutable, which
+//! is the default, and cannot be combined with `#[typemap_mut]`.
+//!
+//! Values can also be assigned by type with [`set!`], or with `#[typemap(put)]`, through the
+//! generated `put` method, which can be chained:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap, Default)]
+//! #[typemap(mut, put)]
+//! struct Context(u32, &'static str);
+//!
+//! let mut c = Context::default();
+//! c.put(1u32).put("name");
+//! assert_eq!((*get!(c, u32), *get!(c, &str)), (1, "name"));
+//! ```
+//!
+//! With `#[typemap(split)]`, the generated `split_mut` method mutably borrows all the mutable
+//! fields at once, so that they can be handed to different parts of the code:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(mut, split)]
+//! struct Context(u32, &'static str);
+//!
+//! let mut c = Context(1, "name");
+//! let (count, name) = c.split_mut();
+//! *count += name.len() as u32;
+//! *name = "other";
+//! assert_eq!((c.0, c.1), (5, "other"));
+//! ```
+//!
+//! With `#[typemap(query)]`, several fields can be borrowed at once with [`query!`], some of
+//! them mutably, the distinctness of the requested types being checked at compile time:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! struct Config(u32);
+//! struct Counter(u32);
+//!
+//! #[derive(Typemap)]
+//! #[typemap(query)]
+//! struct Context(Config, #[typemap(mut)] Counter, &'static str);
+//!
+//! let mut c = Context(Config(2), Counter(1), "name");
+//! let (config, counter, name) = query!(c, &Config, &mut Counter, &&str);
+//! counter.0 += config.0 * name.len() as u32;
+//! assert_eq!(c.1 .0, 9);
+//! ```
+//!
+//! With `#[typemap(visit)]`, the generated `accept` method calls a [`TypemapVisitor`] on all
+//! fields, in declaration order, so that generic code can process arbitrary typemaps:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! use std::fmt::Debug;
+//!
+//! struct Dump(Vec<String>);
+//!
+//! impl<T: Debug> TypemapVisitor<T> for Dump {
+//!     fn visit(&mut self, value: &T) {
+//!         self.0.push(format!("{value:?}"));
+//!     }
+//! }
+//!
+//! #[derive(Typemap)]
+//! #[typemap(visit)]
+//! struct Context(u32, &'static str);
+//!
+//! let mut dump = Dump(vec![]);
+//! Context(1, "name").accept(&mut dump);
+//! assert_eq!(dump.0, ["1", "\"name\""]);
+//! ```
+//!
+//! With `#[typemap_dyn(Trait, ...)]`, a `for_each_trait` method is generated for each trait,
+//! calling a closure on all fields as trait objects, for instance to log them:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! use std::fmt::{Debug, Display};
+//!
+//! #[derive(Typemap)]
+//! #[typemap_dyn(Debug, Display)]
+//! struct Context(u32, &'static str);
+//!
+//! let c = Context(1, "name");
+//! let mut fields = vec![];
+//! c.for_each_debug(|field| fields.push(format!("{field:?}")));
+//! c.for_each_display(|field| fields.push(format!("{field}")));
+//! assert_eq!(fields, ["1", "\"name\"", "1", "name"]);
+//! ```
+//!
+//! A `map_trait` method is generated as well, returning the result of the closure for each field
+//! along with the name of its type, for instance to aggregate health checks into a report:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! trait HealthCheck {
+//!     fn check(&self) -> Result<(), &'static str>;
+//! }
+//! struct Database(bool);
+//! impl HealthCheck for Database {
+//!     fn check(&self) -> Result<(), &'static str> {
+//!         self.0.then_some(()).ok_or("disconnected")
+//!     }
+//! }
+//! struct Cache;
+//! impl HealthCheck for Cache {
+//!     fn check(&self) -> Result<(), &'static str> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[derive(Typemap)]
+//! #[typemap_dyn(HealthCheck)]
+//! struct Resources(Database, Cache);
+//!
+//! let report = Resources(Database(false), Cache).map_health_check(|r| r.check());
+//! assert_eq!(report.len(), 2);
+//! assert!(report[0].0.ends_with("Database"));
+//! assert_eq!((report[0].1, report[1].1), (Err("disconnected"), Ok(())));
+//! ```
+//!
+//! With `#[typemap(types)]`, the typemap implements the [`Typemap`] umbrella trait, whose
+//! `Types` associated type is the tuple of the types of its fields, allowing generic code to
+//! enumerate them:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! fn pair<M: Typemap<Types = (A, B)>, A: Default, B: Default>() -> (A, B) {
+//!     Default::default()
+//! }
+//!
+//! #[derive(Typemap)]
+//! #[typemap(types)]
+//! struct Context(u32, &'static str);
+//!
+//! assert_eq!(pair::<Context, _, _>(), (0, ""));
+//! ```
+//!
+//! The trait also provides the number of fields and the names of their types, for diagnostics:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! fn describe<M: Typemap>() -> String {
+//!     format!("{} fields: {}", M::LEN, M::type_names().collect::<Vec<_>>().join(", "))
+//! }
+//!
+//! #[derive(Typemap)]
+//! #[typemap(types)]
+//! struct Context(u32, &'static str);
+//!
+//! assert_eq!(describe::<Context>(), "2 fields: u32, &str");
+//! ```
+//!
+//! With `#[typemap(view)]`, a `{Name}Ref` view struct borrowing the fields is generated, which
+//! is cheaply copyable and implements [`Get`], and is obtained with the `as_view` method:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(view)]
+//! struct Context(u32, &'static str);
+//!
+//! fn name(view: ContextRef) -> &str {
+//!     get!(view, &str)
+//! }
+//!
+//! let c = Context(1, "name");
+//! let view = c.as_view();
+//! assert_eq!((name(view), *get!(view, u32)), ("name", 1));
+//! ```
+//!
+//! Similarly, `#[typemap(view_mut)]` generates a `{Name}Mut` view struct, mutably borrowing the
+//! mutable fields and implementing [`GetMut`] for them, obtained with the `as_view_mut` method.
+//!
+//! With `#[typemap(inherent)]`, fields are also accessible through the generic `get` and, for
+//! mutable typemaps, `get_mut` methods:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(mut, inherent)]
+//! struct Context(u32, &'static str);
+//!
+//! let mut c = Context(1, "name");
+//! *c.get_mut::<u32>() += 1;
+//! assert_eq!((*c.get::<u32>(), *c.get::<&str>()), (2, "name"));
+//! ```
+//!
+//! Instead of listing the types a generic function needs, `#[typemap_alias(Name)]` generates a
+//! trait requiring all the types of the typemap, implemented by all the typemaps containing them:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! struct Db(u32);
+//! struct Cache(u32);
+//!
+//! #[derive(Typemap)]
+//! #[typemap_alias(AppDeps)]
+//! struct Deps(Db, Cache);
+//!
+//! fn run(deps: &impl AppDeps) -> u32 {
+//!     get!(deps, Db).0 + get!(deps, Cache).0
+//! }
+//!
+//! #[derive(Typemap)]
+//! struct App(Db, Cache, &'static str);
+//!
+//! assert_eq!(run(&App(Db(1), Cache(2), "app")), 3);
+//! assert_eq!(run(&Deps(Db(3), Cache(4))), 7);
+//! ```
+//!
+//! To pass a typemap to generic APIs taking `impl AsRef<T>`, `#[typemap_asref]` additionally
+//! implements [`AsRef`] for its field types, and [`AsMut`] for the mutable ones:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! struct Db(u32);
+//!
+//! fn query(db: impl AsRef<Db>) -> u32 {
+//!     db.as_ref().0
+//! }
+//!
+//! #[derive(Typemap)]
+//! #[typemap_asref]
+//! struct App(Db, &'static str);
+//!
+//! assert_eq!(query(&App(Db(1), "app")), 1);
+//! ```
+//!
+//! Similarly, `#[typemap_borrow]` implements [`Borrow`](core::borrow::Borrow), and
+//! [`BorrowMut`](core::borrow::BorrowMut) for the mutable field types.
+//!
+//! For APIs converting their arguments with [`Into`], `#[typemap(into_ref)]` converts references
+//! to the typemap into references to its field types:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! struct Db(u32);
+//!
+//! fn query<'a>(db: impl Into<&'a Db>) -> u32 {
+//!     db.into().0
+//! }
+//!
+//! #[derive(Typemap)]
+//! #[typemap(into_ref)]
+//! struct App(Db, &'static str);
+//!
+//! assert_eq!(query(&App(Db(1), "app")), 1);
+//! ```
+//!
+//! With `#[typemap(tuple)]`, a typemap converts from and to the tuple of its field types:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(tuple)]
+//! struct Context(u32, &'static str);
+//!
+//! let c = Context::from((1, "name"));
+//! let (id, name): (u32, &str) = c.into();
+//! assert_eq!((id, name), (1, "name"));
+//! ```
+//!
+//! A typemap can also be converted into other ones with `#[typemap(into(Target))]`, each field
+//! of the target being converted with [`From`] from the only field of the source whose type
+//! converts into its type, while the other fields of the source are dropped:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! struct DbConfig(&'static str);
+//! struct Db(String);
+//!
+//! impl From<DbConfig> for Db {
+//!     fn from(config: DbConfig) -> Self {
+//!         Db(config.0.to_owned())
+//!     }
+//! }
+//!
+//! #[derive(Typemap)]
+//! #[typemap(into(Domain))]
+//! struct Dto(u32, DbConfig, &'static str);
+//!
+//! #[derive(Typemap)]
+//! struct Domain(Db, u32);
+//!
+//! let domain = Domain::from(Dto(1, DbConfig("db"), "dropped"));
+//! assert_eq!((get!(domain, Db).0.as_str(), *get!(domain, u32)), ("db", 1));
+//! ```
+//! The conversion fails to compile if a field of the target has no field to be converted from,
+//! or several ones, for instance `u64` from both `u8` and `u32`:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(into(Domain))]
+//! struct Dto(u32);
+//!
+//! #[derive(Typemap)]
+//! struct Domain(u32, &'static str);
+//! ```
+//! It also fails to compile if a field of the source is the only one to be converted into
+//! several fields of the target, as it can only be moved once:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(into(Domain))]
+//! struct Dto(u8);
+//!
+//! #[derive(Typemap)]
+//! struct Domain(u16, u32);
+//!
+//! let domain = Domain::from(Dto(1));
+//! ```
+//!
+//! Structs with named fields are typemaps as well, their fields being accessed by type only:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(mut)]
+//! struct State {
+//!     count: i32,
+//!     ratio: f32,
+//! }
+//!
+//! let mut s = State { count: 1, ratio: 2.0 };
+//! *get_mut!(s, i32) += 1;
+//! assert_eq!(s.count, 2);
+//! assert_eq!(*get!(s, f32), 2.0);
+//! ```
+//!
+//! Alternatively, only some fields of a struct can be exposed with the [`typemap_fields`]
+//! attribute, leaving the struct and its other fields untouched:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[typemap_fields(count, name)]
+//! struct State {
+//!     count: i32,
+//!     name: &'static str,
+//!     total: i32,
+//! }
+//!
+//! let s = State { count: 1, name: "state", total: 2 };
+//! assert_eq!(*get!(s, i32), 1);
+//! assert_eq!(*get!(s, &str), "state");
+//! assert_eq!(s.total, 2);
+//! ```
+//! The same attribute is available as `#[typemap(fields(...))]` from the [`attribute`] module:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[typemap_meta::attribute::typemap(fields(count, name))]
+//! struct State {
+//!     count: i32,
+//!     name: &'static str,
+//!     total: i32,
+//! }
+//!
+//! let s = State { count: 1, name: "state", total: 2 };
+//! assert_eq!(*get!(s, i32), 1);
+//! ```
+//!
+//! Enums whose variants hold disjoint types can derive `Typemap` as well, implementing
+//! [`TryGet`] for these types, which returns the value of the active variant:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! enum Payload {
+//!     Text(&'static str),
+//!     Number(i32),
+//!     Empty,
+//! }
+//!
+//! let p = Payload::Number(1);
+//! assert_eq!(TryGet::<i32>::try_get(&p), Some(&1));
+//! assert_eq!(TryGet::<&str>::try_get(&p), None);
+//! ```
+//! Besides `mut` and `readonly`, selecting whether [`TryGetMut`] is implemented, as well as
+//! `bound` and `crate`, the options of structs do not apply to enums and are rejected:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(serde)]
+//! enum Payload {
+//!     Text(&'static str),
+//!     Number(i32),
+//! }
+//! ```
+//!
+//! All options can also be given through the `#[typemap(...)]` attribute, which is checked
+//! strictly so that misspelled options are reported. For instance, `#[typemap(mut)]` is
+//! equivalent to `#[typemap_mut]`, and a field can be excluded from the map with `skip`, or
+//! equivalently `#[typemap_skip]`:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(mut)]
+//! struct Test(i32, #[typemap(skip)] f32);
+//!
+//! let mut t = Test(1, 2.0);
+//! *get_mut!(t, i32) = 3;
+//! assert_eq!(*get!(t, i32), 3);
+//! ```
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(mutable)]
+//! struct Test(i32, f32);
+//! ```
+//! See [`Typemap`](macro@Typemap) for the full list of options.
+//!
+//! Mutability can also be chosen per field, with `mut` on the fields to expose mutably, or
+//! `readonly` on the fields to keep immutable in a `mut` struct:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(mut)]
+//! struct Context(u32, #[typemap(readonly)] &'static str);
+//!
+//! let mut c = Context(1, "name");
+//! *get_mut!(c, u32) = 2;
+//! assert_eq!(*get!(c, &str), "name");
+//! ```
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! # #[derive(Typemap)]
+//! # #[typemap(mut)]
+//! # struct Context(u32, #[typemap(readonly)] &'static str);
+//! # let mut c = Context(1, "name");
+//! *get_mut!(c, &str) = "other";
+//! ```
+//!
+//! Fields of type `PhantomData` only serve as markers, so they are skipped unless marked
+//! with `include`.
+//!
+//! As types are the keys of the map, the fields must have distinct types, otherwise the derive
+//! reports an error pointing at the fields sharing a type:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct Context(i32, &'static str, i32);
+//! ```
+//!
+//! Generic typemaps are supported, provided that their fields have distinct types for every
+//! instantiation. Hence, a field whose type is a type parameter is rejected if there are other
+//! fields, as it could be instantiated with their type, unless they contain this parameter:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct Context<T>(i32, T);
+//! ```
+//! The same holds for fields whose types only differ by their parameters, or by lifetimes, which
+//! do not distinguish types:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct Context<T>(Vec<T>, Vec<i32>);
+//! ```
+//! This also applies to the types through which fields are accessed by other traits, such as
+//! the inner types of optional fields with [`TryGet`]:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct Context<T>(Option<T>, i32);
+//! ```
+//! As the check is syntactic, types written differently, for instance through an alias or
+//! another path, are deemed distinct, and then rejected by the compiler with conflicting
+//! implementations if they are the same.
+//! Checking instead the types of the fields once instantiated is not possible, as stable Rust
+//! cannot compare `TypeId`s in constant evaluation, and would then only panic at runtime.
+//! As volatile fields are accessed through other traits than the others, they only need to have
+//! distinct types among themselves, and can share the type of another field:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct Registers<T>(T, #[typemap(volatile)] u16);
+//!
+//! let r = Registers(1u16, 2);
+//! assert_eq!((*get!(r, u16), read_volatile!(r, u16)), (1, 2));
+//! ```
+//!
+//! Bounds required by the generated items but not by the struct itself can be added with
+//! `#[typemap(bound = "...")]`, for instance for looking up fields as `Any`, which requires
+//! `'static` types:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(any, bound = "T: 'static")]
+//! struct Context<T>(i32, [T; 2]);
+//!
+//! let c = Context(1, [2u8, 3]);
+//! assert_eq!(c.lookup::<i32>(), Some(&1));
+//! ```
+//!
+//! Typemaps layered on top of each other, for instance a request context over an application
+//! one, can be queried with `get_layered!`, which selects at compile time the first layer
+//! containing the type:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct App(u32, &'static str);
+//! #[derive(Typemap)]
+//! struct Request(&'static str, bool);
+//!
+//! let app = App(1, "app");
+//! let request = Request("request", true);
+//! assert_eq!(*get_layered!(request, app, &str), "request");
+//! assert_eq!(*get_layered!(request, app, u32), 1);
+//! ```
+//! In generic code, a layer whose type is a type parameter is only known to contain the types
+//! required by its bounds, so the outer layers are used for the others, even if the instantiated
+//! layer contains them:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! # #[derive(Typemap)]
+//! # struct App(u32, &'static str);
+//! #[derive(Typemap)]
+//! struct Request(&'static str, u32);
+//!
+//! fn unbounded<R>(request: &R, app: &App) -> u32 {
+//!     *get_layered!(request, app, u32)
+//! }
+//! fn bounded<R: Get<u32>>(request: &R, app: &App) -> u32 {
+//!     *get_layered!(request, app, u32)
+//! }
+//!
+//! let (app, request) = (App(1, "app"), Request("request", 2));
+//! assert_eq!(unbounded(&request, &app), 1);
+//! assert_eq!(bounded(&request, &app), 2);
+//! ```
+//!
+//! Alternatively, a typemap can contain the other one in a field marked with
+//! `#[typemap_flatten]`, and then provides the types of the nested typemap in addition to its
+//! own ones. At most one field can be flattened, and its type cannot be a type parameter:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct App(u32, &'static str);
+//! #[derive(Typemap)]
+//! struct Request(#[typemap_flatten] App, bool);
+//!
+//! let request = Request(App(1, "app"), true);
+//! assert_eq!(*get!(request, u32), 1);
+//! assert_eq!(*get!(request, &str), "app");
+//! assert!(*get!(request, bool));
+//! ```
+//!
+//! As the nested typemap is accessed through a blanket implementation, which the compiler
+//! only accepts if the nested typemap provides none of the types of the other fields, these
+//! types must be distinct, or the implementations conflict:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct App(u32);
+//! #[derive(Typemap)]
+//! struct Request(#[typemap_flatten] App, u32);
+//! ```
+//!
+//! Fields of type `Option<T>` can be probed for a `T` using [`TryGet`], or [`TryGetMut`] for
+//! mutable typemaps, for instance with `get_or_default!`, which falls back to the default
+//! value of `T`:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Default)]
+//! struct Limits(u32);
+//!
+//! #[derive(Typemap)]
+//! struct Config(Option<Limits>, i32);
+//!
+//! let c = Config(None, 1);
+//! assert_eq!(get_or_default!(c, Limits).0, 0);
+//! assert_eq!(*get_or_default!(c, i32), 1);
+//! ```
+//!
+//! Large or rarely accessed fields can be declared as `Box<T>` and marked with `boxed`,
+//! keeping the map compact while their accessors remain keyed on `T`:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(mut)]
+//! struct State(u32, #[typemap(boxed)] Box<[u8; 4096]>);
+//!
+//! let mut s = State(1, Box::new([0; 4096]));
+//! get_mut!(s, [u8; 4096])[0] = 2;
+//! assert_eq!(get!(s, [u8; 4096])[0], 2);
+//! ```
+//!
+//! Similarly, fields accessed concurrently can be declared as [`CachePadded<T>`] and marked
+//! with `pad`, so that they do not share a cache line with their neighbours:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! use core::sync::atomic::{AtomicU32, Ordering};
+//!
+//! #[derive(Typemap)]
+//! struct Counters(#[typemap(pad)] CachePadded<AtomicU32>, #[typemap(pad)] CachePadded<u64>);
+//!
+//! let c = Counters(CachePadded::new(AtomicU32::new(1)), CachePadded::new(2));
+//! get!(c, AtomicU32).fetch_add(1, Ordering::Relaxed);
+//! assert_eq!(get!(c, AtomicU32).load(Ordering::Relaxed), 2);
+//! assert_eq!(*get!(c, u64), 2);
+//! ```
+//!
+//! The size of the struct and of its fields can be bounded with `max_size`, which fails the
+//! build, reporting the actual size, if a budget is exceeded:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(max_size = 16)]
+//! struct Hot(u64, #[typemap(max_size = 8)] [u8; 16]);
+//! ```
+//! As the size of a generic struct depends on its parameters, its budget is checked for the
+//! instantiations listed with `instantiate`:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(max_size = 24, instantiate("Hot<f32>", "Hot<f64>"))]
+//! struct Hot<F>(u32, [F; 2]);
+//! ```
+//!
+//! Typemaps shared across an FFI or shared-memory boundary can use `#[typemap(ffi)]`, which
+//! requires `#[repr(C)]` and fields implementing [`FfiSafe`], and provides the layout of the
+//! fields as the `LAYOUT` constant:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(ffi)]
+//! #[repr(C)]
+//! struct Shared(u8, u32);
+//!
+//! assert_eq!(Shared::LAYOUT[1], FieldLayout { name: "u32", offset: 4, size: 4, align: 4 });
+//! ```
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(ffi)]
+//! #[repr(C)]
+//! struct Shared(u8, &'static str);
+//! ```
+//! This includes `char`, as foreign code can store values in it that are not valid in Rust:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(ffi)]
+//! #[repr(C)]
+//! struct Shared(u8, char);
+//! ```
+//!
+//! Note that without `#[repr(C)]`, the compiler already reorders the fields to minimize padding,
+//! so the declaration order of the fields of a typemap does not affect its size.
+//!
+//! Typemaps reused between requests can be reinitialized with `#[typemap(reset)]`, which
+//! generates `clear_all`, setting all `Option` fields to `None`, and `reset`, setting all fields
+//! to the value given with `default`, or to their default value:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(reset)]
+//! struct Context(Option<u32>, #[typemap(default = "8")] usize, f32);
+//!
+//! let mut c = Context(Some(1), 2, 3.0);
+//! c.clear_all();
+//! assert_eq!(*get!(c, Option<u32>), None);
+//! assert_eq!(*get!(c, usize), 2);
+//! c.reset();
+//! assert_eq!(*get!(c, usize), 8);
+//! assert_eq!(*get!(c, f32), 0.0);
+//! ```
+//!
+//! Fields of type `Vec<T>` or `heapless::Vec<T, N>` hold all the values of type `T`, and can be
+//! accessed without naming the collection type with [`GetAll`] and, if the struct is mutable,
+//! [`GetAllMut`] and [`Push`]. Bounded vectors are recognized by their `heapless` path, while
+//! vectors with another second argument, such as an allocator, are plain fields:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(mut)]
+//! struct Handlers(Vec<fn(u32) -> u32>, i32);
+//!
+//! let mut h = Handlers(Vec::new(), 0);
+//! push!(h, fn(u32) -> u32, |x| x + 1).unwrap();
+//! push!(h, fn(u32) -> u32, |x| x * 2).unwrap();
+//! let results: Vec<_> = get_all!(h, fn(u32) -> u32).iter().map(|f| f(3)).collect();
+//! assert_eq!(results, [4, 6]);
+//! ```
+//!
+//! Atomic fields marked with `atomic` can be loaded and stored through the type of their value
+//! using [`AtomicGet`], without naming the atomic type, and from a shared reference:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+//!
+//! #[derive(Typemap)]
+//! struct Stats(#[typemap(atomic)] AtomicU32, #[typemap(atomic)] AtomicBool);
+//!
+//! let stats = Stats(AtomicU32::new(1), AtomicBool::new(false));
+//! store!(stats, u32, 2);
+//! store!(stats, bool, true, Ordering::Release);
+//! assert_eq!(load!(stats, u32), 2);
+//! assert!(load!(stats, bool, Ordering::Acquire));
+//! ```
+//!
+//! With the `embassy` feature, fields of type `Mutex<M, T>` of `embassy-sync` can be locked
+//! through the type of their value using `MutexGet`, for instance with `lock!`, which evaluates
+//! to a future resolving to the guard. Mutexes are recognized by their `embassy_sync` path, or
+//! by the `#[typemap(embassy)]` field option when imported:
+//! ```
+//! # use typemap_meta::*;
+//! # #[cfg(all(feature = "embassy", feature = "derive"))]
+//! # {
+//! use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+//! use embassy_sync::mutex::Mutex;
+//!
+//! #[derive(Typemap)]
+//! struct Shared(
+//!     embassy_sync::mutex::Mutex<NoopRawMutex, u32>,
+//!     #[typemap(embassy)] Mutex<NoopRawMutex, [u8; 4]>,
+//! );
+//!
+//! async fn tick(shared: &Shared) {
+//!     *lock!(*shared, u32).await += 1;
+//!     lock!(*shared, [u8; 4]).await[0] = 1;
+//! }
+//!
+//! let shared = Shared(Mutex::new(1), Mutex::new([0; 4]));
+//! futures::executor::block_on(tick(&shared));
+//! assert_eq!(*shared.0.try_lock().unwrap(), 2);
+//! # }
+//! ```
+//!
+//! Fields modelling memory-mapped registers can be marked with `#[typemap_volatile]`.
+//! They are then not accessible through references, but only by value using
+//! `read_volatile!` and, if the struct has `#[typemap_mut]`, `write_volatile!`,
+//! so that the compiler never caches or elides the accesses:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap_mut]
+//! struct Peripheral(#[typemap_volatile] u32, f32);
+//!
+//! let mut p = Peripheral(1, 2.0);
+//! assert_eq!(read_volatile!(p, u32), 1);
+//! write_volatile!(p, u32, 3);
+//! assert_eq!(read_volatile!(p, u32), 3);
+//! assert_eq!(*get!(p, f32), 2.0);
+//! ```
+//!
+//! With `#[typemap_bytes]`, the struct implements [`AsBytes`], a fixed-size binary
+//! encoding that does not need allocation, with fields laid out in declaration order:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap_bytes]
+//! struct Settings(u16, [u8; 2], bool);
+//!
+//! let mut bytes = [0; Settings::SIZE];
+//! Settings(1, [2, 3], true).to_bytes(&mut bytes);
+//! assert_eq!(bytes, [1, 0, 2, 3, 1]);
+//!
+//! let s = Settings::from_bytes(&bytes);
+//! assert_eq!(*get!(s, [u8; 2]), [2, 3]);
+//! ```
+//!
+//! Libraries exposing a typemap can add `#[typemap(sealed)]` to prevent downstream crates
+//! from implementing [`Get`] or [`GetMut`] for other types on it, so that new fields can be
+//! added later without breaking these crates.
+//! The sealed typemap can still be used as any other one:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(sealed)]
+//! #[typemap_mut]
+//! pub struct Context(i32, f32);
+//!
+//! let mut c = Context(1, 2.0);
+//! *get_mut!(c, i32) = 3;
+//! assert_eq!(*get!(c, i32), 3);
+//! ```
+//! Other crates cannot implement [`Get`] on it though, even for their own types:
+//! ```compile_fail
+//! # use typemap_meta::*;
+//! # use typemap_meta::__private::SealedExample as Context;
+//! struct Local;
+//!
+//! impl Get<Local> for Context {
+//!     fn get(&self) -> &Local {
+//!         &Local
+//!     }
+//! }
+//! ```
+//!
+//! With `#[typemap(mem_usage)]`, the typemap implements [`MemUsage`] by summing the usage of
+//! all its fields implementing it, skipping the others, and provides a per-type breakdown:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! struct Cache([u8; 16]);
+//! impl MemUsage for Cache {
+//!     fn mem_usage(&self) -> usize {
+//!         self.0.len()
+//!     }
+//! }
+//!
+//! #[derive(Typemap)]
+//! #[typemap(mem_usage)]
+//! struct Resources(Cache, i32);
+//!
+//! let r = Resources(Cache([0; 16]), 1);
+//! assert_eq!(r.mem_usage(), 16);
+//! assert_eq!(r.mem_usage_breakdown()[0].1, Some(16));
+//! assert_eq!(r.mem_usage_breakdown()[1], ("i32", None));
+//! ```
+//!
+//! With `#[typemap(fingerprint)]`, the typemap implements [`Fingerprint`], whose constant
+//! hashes the ordered list of the type names of its fields. Processes exchanging serialized
+//! typemaps can compare fingerprints to check that they agree on the layout before decoding:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(fingerprint)]
+//! struct Sent(u32, [u8; 4]);
+//!
+//! #[derive(Typemap)]
+//! #[typemap(fingerprint)]
+//! struct Received(u32, [u8; 4]);
+//!
+//! assert_eq!(Sent::FINGERPRINT, Received::FINGERPRINT);
+//! ```
+//!
+//! With `#[typemap(display)]`, the typemap implements `Display` as a compact single line, listing
+//! the type names of its fields followed by the values of those implementing `Display`:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! struct Limits(u32);
+//!
+//! #[derive(Typemap)]
+//! #[typemap(display)]
+//! struct Context(u32, Limits, &'static str);
+//!
+//! let c = Context(1, Limits(2), "name");
+//! assert_eq!(c.to_string(), "Context(u32: 1, Limits, &'static str: name)");
+//! ```
+//!
+//! With `#[typemap(cell)]`, a `{Name}Cell` companion struct is generated, wrapping each field
+//! in a `Cell`, or a `RefCell` for fields with `#[typemap(refcell)]`, so that it can be mutated
+//! through shared references. It implements [`Get`] for the wrapped types and [`CellGet`] for the
+//! types of the values, has `get`, `set` and `replace` methods taking the type of the value as a
+//! generic parameter, and converts from and to the typemap:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! # use core::cell::{Cell, RefCell};
+//! #[derive(Typemap)]
+//! #[typemap(cell)]
+//! struct Context(u32, #[typemap(refcell)] [u8; 4], #[typemap(refcell)] &'static str);
+//!
+//! fn tick(c: &ContextCell) {
+//!     c.set::<u32>(c.get::<u32>() + 1);
+//!     get!(*c, RefCell<[u8; 4]>).borrow_mut()[0] = 3;
+//!     assert_eq!(c.replace::<&str>("new"), "old");
+//! }
+//!
+//! let c = ContextCell::from(Context(1, [0; 4], "old"));
+//! tick(&c);
+//! let c = Context::from(c);
+//! assert_eq!(*get!(c, u32), 2);
+//! assert_eq!(*get!(c, [u8; 4]), [3, 0, 0, 0]);
+//! assert_eq!(*get!(c, &str), "new");
+//! ```
+//!
+//! Changes to a typemap can be observed by wrapping it in [`Observed`], along with a registry
+//! of [`Subscribers`], itself a typemap:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! # use core::sync::atomic::{AtomicI32, Ordering};
+//! static LAST: AtomicI32 = AtomicI32::new(0);
+//!
+//! #[derive(Typemap)]
+//! #[typemap(mut)]
+//! struct Test(i32, f32);
+//! #[derive(Typemap, Default)]
+//! #[typemap(mut)]
+//! struct Registry(Subscribers<i32>);
+//!
+//! let mut t = Observed::new(Test(1, 2.0), Registry::default());
+//! t.subscribe(|v: &i32| LAST.store(*v, Ordering::Relaxed)).unwrap();
+//! t.update(|v: &mut i32| *v = 3);
+//! assert_eq!(LAST.load(Ordering::Relaxed), 3);
+//! assert_eq!(*get!(t, i32), 3);
+//! ```
+//!
+//! With `#[typemap(commands)]`, mutations can be recorded as typed commands, for instance
+//! to be replayed onto another instance:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap, Debug, PartialEq)]
+//! #[typemap(mut, commands(Clone, Copy, Debug))]
+//! struct Test(i32, f32);
+//!
+//! let mut commands = [TestCommand::Field0(0); 2];
+//! let mut count = 0;
+//! let mut t = Test(1, 2.0);
+//! let mut recorder = Recorder::new(&mut t, |command: &TestCommand| {
+//!     commands[count] = command.clone();
+//!     count += 1;
+//! });
+//! recorder.set(3);
+//! recorder.update(|v: &mut f32| *v *= 2.0);
+//!
+//! let mut replica = Test(1, 2.0);
+//! for command in commands {
+//!     replica.apply(command);
+//! }
+//! assert_eq!(replica, t);
+//! ```
+//!
+//! With the `alloc` feature and `#[typemap(freeze)]`, a typemap can be turned into a `Frozen`
+//! one once built, cheaply cloneable and implementing [`Get`]. It can be thawed back into the
+//! owned typemap once the other clones are dropped:
+//! ```
+//! # use typemap_meta::*;
+//! # #[cfg(all(feature = "alloc", feature = "derive"))]
+//! # {
+//! #[derive(Typemap)]
+//! #[typemap(freeze)]
+//! struct Context(u32, &'static str);
+//!
+//! let frozen = Context(1, "name").freeze();
+//! let shared = frozen.clone();
+//! assert_eq!(*get!(shared, u32), 1);
+//! drop(shared);
+//! let c = frozen.thaw().ok().unwrap();
+//! assert_eq!(*get!(c, &str), "name");
+//! # }
+//! ```
+//!
+//! The `alloc` feature also makes typemaps owned by a `Box`, an `Rc` or an `Arc` accessible
+//! without dereferencing them:
+//! ```
+//! # use typemap_meta::*;
+//! # #[cfg(all(feature = "alloc", feature = "derive"))]
+//! # {
+//! # extern crate alloc;
+//! use alloc::sync::Arc;
+//!
+//! #[derive(Typemap)]
+//! struct Context(u32, &'static str);
+//!
+//! let shared = Arc::new(Context(1, "name"));
+//! assert_eq!(*get!(shared, u32), 1);
+//! # }
+//! ```
+//!
+//! With the `rcu` feature, read-mostly typemaps can be shared as immutable snapshots: a
+//! `Publisher` atomically publishes new versions of the typemap, while its `Reader`s get
+//! consistent `Snapshot`s implementing [`Get`].
+//!
+//! Conversions between versions of a typemap can be generated with [`migrate!`], which
+//! requires every removed type to be explicitly handled:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct MapV1(i32, u8, &'static str);
+//! #[derive(Typemap)]
+//! struct MapV2(i32, u16, f32);
+//!
+//! migrate! {
+//!     MapV1(i32, u8, &'static str) => MapV2(i32, u16, f32);
+//!     convert u8 => u16;
+//!     drop &'static str;
+//! }
+//!
+//! let v2 = MapV2::from(MapV1(1, 2, "old"));
+//! assert_eq!(*get!(v2, u16), 2);
+//! assert_eq!(*get!(v2, f32), 0.0);
+//! ```
+//! Each type of the source can only be handled once, so it cannot be both moved and converted,
+//! or converted twice:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! # #[derive(Typemap)]
+//! # struct MapV1(i32, u8);
+//! # #[derive(Typemap)]
+//! # struct MapV2(i32, u16, i64);
+//! migrate! {
+//!     MapV1(i32, u8) => MapV2(i32, u16, i64);
+//!     convert u8 => u16;
+//!     convert u8 => i64;
+//! }
+//! ```
+//!
+//! An owned subset of a typemap, for instance to be moved to another thread, can be obtained
+//! with `#[typemap(extract)]`, which generates `extracted_from`, cloning the fields from any
+//! typemap containing their types:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct Context(u32, String, f32);
+//!
+//! #[derive(Typemap)]
+//! #[typemap(extract)]
+//! struct Task(String, u32);
+//!
+//! let context = Context(1, "name".to_string(), 2.0);
+//! let task = Task::extracted_from(&context);
+//! assert_eq!(*get!(task, String), "name");
+//! ```
+//!
+//! A typemap can also be defined by listing its types with [`typemap!`], which generates the
+//! tuple struct deriving [`Typemap`], and optionally a constructor:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! # #[derive(Default)]
+//! # struct Config;
+//! # #[derive(Default)]
+//! # struct DbPool;
+//! typemap! {
+//!     #[typemap(mut)]
+//!     pub struct AppCtx { Config, DbPool, u32 }
+//!     pub fn new;
+//! }
+//!
+//! let mut ctx = AppCtx::new(Config, DbPool, 1);
+//! *get_mut!(ctx, u32) += 1;
+//! assert_eq!(*get!(ctx, u32), 2);
+//! ```
+//!
+//! Functions generic over typemaps can be called without a long-lived typemap by building
+//! an anonymous one over references with [`borrow_map!`], giving the type of every value:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! fn tick(mut ctx: impl Get<u32> + GetMut<Vec<u32>>) {
+//!     let step = *get!(ctx, u32);
+//!     get_mut!(ctx, Vec<u32>).push(step);
+//! }
+//!
+//! let step = 2;
+//! let mut history = Vec::new();
+//! tick(borrow_map!(&step: u32, &mut history: Vec<u32>));
+//! assert_eq!(history, [2]);
+//! ```
+//!
+//! The types of a typemap absent in another one can be gathered in a new typemap with
+//! [`difference!`], which also generates the extraction from the first typemap:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! struct Context(i32, u8, &'static str);
+//! #[derive(Typemap)]
+//! struct Shared(u8, f32);
+//!
+//! difference! {
+//!     struct Local = Context(i32, u8, &'static str) - Shared(u8, f32);
+//! }
+//!
+//! let local = Local::from(Context(1, 2, "local"));
+//! assert_eq!(*get!(local, i32), 1);
+//! assert_eq!(*get!(local, &str), "local");
+//! ```
+//! Both typemaps must be declared with the types of their fields, in order:
+#![cfg_attr(feature = "derive", doc = "```compile_fail")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! # #[derive(Typemap)]
+//! # struct Context(i32, u8, &'static str);
+//! # #[derive(Typemap)]
+//! # struct Shared(u8, f32);
+//! difference! {
+//!     struct Local = Context(i32, u8, &'static str) - Shared(u8, f64);
+//! }
+//! ```
+//!
+//! With `#[typemap(any)]`, the typemap implements [`AnyTypemap`], a facade for looking up
+//! values by type at run time, also implemented for `type_map::TypeMap` with the `type-map`
+//! feature. This allows libraries to accept both static and runtime typemaps:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! fn port(map: &impl AnyTypemap) -> u16 {
+//!     map.lookup::<u16>().copied().unwrap_or(80)
+//! }
+//!
+//! #[derive(Typemap)]
+//! #[typemap(any)]
+//! struct Config(u16, &'static str);
+//!
+//! assert_eq!(port(&Config(8080, "localhost")), 8080);
+//! ```
+//!
+//! It also generates `iter_any` and, if the typemap is mutable, `iter_any_mut`, iterating over
+//! all fields as [`Any`](core::any::Any), for instance to apply generic fix-up passes:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(any, mut)]
+//! struct Limits(u16, u32, &'static str);
+//!
+//! let mut limits = Limits(1, 2, "limits");
+//! for field in limits.iter_any_mut() {
+//!     if let Some(limit) = field.downcast_mut::<u32>() {
+//!         *limit *= 10;
+//!     }
+//! }
+//! assert_eq!(*get!(limits, u32), 20);
+//! assert_eq!(limits.iter_any().count(), 3);
+//! ```
+//!
+//! Similarly, `fold_fields` computes an aggregate over all fields, like their total size:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(any)]
+//! struct Limits(u16, u32, [u8; 10]);
+//!
+//! let limits = Limits(1, 2, [0; 10]);
+//! let size = limits.fold_fields(0, |size, field| size + std::mem::size_of_val(field));
+//! assert_eq!(size, 16);
+//! ```
+//!
+//! When the type is only known at run time, `get_any` and, if the typemap is mutable,
+//! `get_any_mut` look up a field by its [`TypeId`](core::any::TypeId):
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! use std::any::TypeId;
+//!
+//! #[derive(Typemap)]
+//! #[typemap(any)]
+//! struct Limits(u16, u32);
+//!
+//! let limits = Limits(1, 2);
+//! let field = limits.get_any(TypeId::of::<u32>()).unwrap();
+//! assert_eq!(field.downcast_ref::<u32>(), Some(&2));
+//! assert!(limits.get_any(TypeId::of::<u8>()).is_none());
+//! ```
+//!
+//! The typemap also implements the object-safe [`DynTypemap`] trait, so that different
+//! typemaps can be stored together and handled through `&dyn DynTypemap`, which implements
+//! [`AnyTypemap`]:
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use typemap_meta::*;
+//! #[derive(Typemap)]
+//! #[typemap(any)]
+//! struct Limits(u16, u32);
+//!
+//! #[derive(Typemap)]
+//! #[typemap(any)]
+//! struct Names(&'static str);
+//!
+//! let maps: [&dyn DynTypemap; 2] = [&Limits(1, 2), &Names("name")];
+//! assert_eq!(maps[0].field_count(), 2);
+//! assert_eq!(maps[1].type_name(0), Some("&str"));
+//! assert_eq!(maps[1].lookup::<&str>(), Some(&"name"));
+//! assert_eq!(maps[1].lookup::<u16>(), None);
+//! ```
+//!
+//! With the `bevy` feature, `#[typemap(bevy)]` generates `insert_into_world`, inserting every
+//! field as a resource of a Bevy `World`, and `extract_from_world`, cloning them back into a
+//! typemap if they are all present.
+//!
+//! With the `frunk` feature, `#[typemap(frunk)]` implements frunk's `Generic`, whose
+//! representation is the HList of all fields, generates `into_hlist` and `from_hlist`, and
+//! implements frunk's `Selector` for the mutable fields, so that HList-generic code can operate
+//! on the typemap directly.
+//!
+//! With the `rayon` feature, `#[typemap(rayon)]` additionally generates, for each trait given
+//! with `dyn`, `par_for_each_trait` and `par_for_each_trait_mut`, which call a closure on the
+//! fields in parallel on the rayon thread pool, for instance to warm several caches at once.
+//!
+//! With the `type-map` feature, `#[typemap(type_map)]` generates `into_type_map`, converting
+//! the typemap into a runtime `type_map::TypeMap` holding every field, for instance to pass it
+//! to code not yet migrated to static typemaps.
+//!
+//! With the `rhai` feature, `#[typemap(rhai)]` generates `register_rhai`, which registers the
+//! typemap in a rhai `Engine` so that scripts can read its fields, and write them if the typemap
+//! is mutable, by indexing it with type names. Integers and floats are converted to and from
+//! the rhai ones, while other fields are passed as opaque values and must implement `Clone`.
+//!
+//! With the `async-graphql` feature, a typemap installed as async-graphql context data can be
+//! accessed by resolvers through `TypemapContext`, which is implemented for the context:
+//! `ctx.get_typed::<Map, T>()` only compiles if `Map` contains a `T`.
+//!
+//! With the `env` feature, fields marked with `env = "NAME"` are read from the environment
+//! variable `NAME` by the generated `from_env` constructor, and parsed with [`FromStr`] or with
+//! the function given by `parse_with`. Other fields get their default value, and all missing
+//! or invalid variables are reported at once in an `EnvError`:
+//! ```ignore
+//! #[derive(Typemap)]
+//! struct Config(
+//!     #[typemap(env = "DATABASE_URL")] DatabaseUrl,
+//!     #[typemap(env = "PORT", parse_with = "parse_port")] Port,
+//!     Metrics,
+//! );
+//!
+//! let config = Config::from_env()?;
+//! ```
+//! The variables can also be provided by a closure with `from_env_with`, for instance to read
+//! them from a configuration file or to test the typemap without altering the environment.
+//!
+//! [`FromStr`]: core::str::FromStr
+//!
+//! With the `manifest` feature, the derive macro also implements [`Manifest`], whose
+//! constant holds a JSON description of the typemap, listing the name, index and
+//! mutability of all its fields. This allows external code generators to process
+//! typemaps without parsing Rust sources.
+//!
+//! With the `serde` feature, `#[typemap(serde)]` implements serde's `Serialize` and
+//! `Deserialize`, with fields named after their types. Individual fields can be customized
+//! with `#[typemap(serde(rename = "name", with = "module", skip, default))]`, skipped fields
+//! being set to their default value when deserializing. Missing fields are an error, except
+//! `Option` ones, which are `None`, and those marked with `default`.
+
+#[cfg(feature = "derive")]
+pub use typemap_meta_derive::*;
+
+/// The [`typemap_fields`] attribute under the name `typemap`, taking the fields as
+/// `#[typemap(fields(...))]`
+///
+/// As the [`typemap!`] macro takes this name at the root of the crate, the attribute is imported
+/// from this module, and then shadows the macro in the importing scope.
+#[cfg(feature = "derive")]
+pub mod attribute {
+    pub use typemap_meta_derive::typemap_fields as typemap;
+}
+
+// the generated code refers to this crate by its absolute path, also within it
+extern crate self as typemap_meta;
+
+// some macros of integrated crates refer to `::std`
+#[cfg(all(test, feature = "async-graphql"))]
+extern crate std;
+
+mod any;
+pub use any::{AnyTypemap, DynTypemap};
+mod atomic;
+pub use atomic::AtomicGet;
+mod cell;
+pub use cell::CellGet;
+mod codec;
+pub use codec::AsBytes;
+mod collection;
+pub use collection::{GetAll, GetAllMut, Push};
+mod command;
+pub use command::{Commands, Recorder, SetCommand};
+mod convert;
+pub use convert::{FieldIndex, FromFields, IntoFields, TakeField};
+#[cfg(feature = "env")]
+mod env;
+#[cfg(feature = "env")]
+pub use env::{EnvError, EnvVarError};
+mod ffi;
+pub use ffi::{FfiSafe, FieldLayout};
+#[cfg(feature = "alloc")]
+mod frozen;
+#[cfg(feature = "alloc")]
+pub use frozen::Frozen;
+#[cfg(feature = "async-graphql")]
+mod graphql;
+#[cfg(feature = "async-graphql")]
+pub use graphql::TypemapContext;
+mod observe;
+pub use observe::{Observed, Subscribers};
+#[cfg(feature = "embassy")]
+mod mutex;
+#[cfg(feature = "embassy")]
+pub use mutex::MutexGet;
+mod pad;
+pub use pad::CachePadded;
+#[cfg(feature = "alloc")]
+mod ptr;
+mod query;
+pub use query::{Query, QueryField, QueryFieldMut, QueryItem};
+#[cfg(feature = "rcu")]
+mod rcu;
+#[cfg(feature = "rcu")]
+pub use rcu::{Publisher, Reader, Snapshot};
+mod visit;
+pub use visit::TypemapVisitor;
+
+/// Helper trait to get a specific type `T` from a tuple struct containing disjoint heterogeneous types
+pub trait Get<T> {
+    fn get(&self) -> &T;
+}
+
+/// Helper trait to mutably get a specific type `T` from a tuple struct containing disjoint heterogeneous types
+pub trait GetMut<T> {
+    fn get_mut(&mut self) -> &mut T;
+}
+
+impl<T, M: Get<T> + ?Sized> Get<T> for &M {
+    fn get(&self) -> &T {
+        (**self).get()
+    }
+}
+
+impl<T, M: Get<T> + ?Sized> Get<T> for &mut M {
+    fn get(&self) -> &T {
+        (**self).get()
+    }
+}
+
+impl<T, M: GetMut<T> + ?Sized> GetMut<T> for &mut M {
+    fn get_mut(&mut self) -> &mut T {
+        (**self).get_mut()
+    }
+}
+
+/// Helper trait to get the field at position `I` of a struct containing disjoint heterogeneous types
+///
+/// It is implemented by the derive macro with `#[typemap(positional)]` for all fields accessible
+/// by reference, `I` being their position in the declaration of the struct.
+pub trait GetAt<const I: usize> {
+    type Output;
+    fn get_at(&self) -> &Self::Output;
+}
+
+/// Helper trait to get a pinned mutable reference to a specific type `T` from a pinned tuple struct containing disjoint heterogeneous types
+///
+/// It is implemented by the derive macro with `#[typemap_pin]`, the fields being structurally pinned.
+pub trait GetPinMut<T> {
+    fn get_pin_mut(self: core::pin::Pin<&mut Self>) -> core::pin::Pin<&mut T>;
+}
+
+/// Helper trait to mutably get two distinct types `A` and `B` from a tuple struct containing disjoint heterogeneous types at the same time
+///
+/// It is implemented by the derive macro with `#[typemap(disjoint)]` for all the pairs of
+/// distinct mutable fields, proving statically that the references do not alias.
+pub trait GetDisjointMut<A, B> {
+    fn get_disjoint_mut(&mut self) -> (&mut A, &mut B);
+}
+
+/// Marker trait of the tuple structs containing a specific type `T`, to express their requirements in bounds
+///
+/// It is implemented for all types implementing [`Get<T>`], which it extends.
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// struct Db(u32);
+/// struct Cache(u32);
+///
+/// fn run<M: Has<Db> + Has<Cache>>(m: &M) -> u32 {
+///     get!(m, Db).0 + get!(m, Cache).0
+/// }
+///
+/// #[derive(Typemap)]
+/// struct App(Db, Cache, &'static str);
+///
+/// assert_eq!(run(&App(Db(1), Cache(2), "app")), 3);
+/// ```
+pub trait Has<T>: Get<T> {}
+
+impl<T, M: Get<T> + ?Sized> Has<T> for M {}
+
+/// Helper trait to get a clone of a specific type `T` from a tuple struct containing disjoint heterogeneous types
+///
+/// It is implemented for all types implementing [`Get<T>`].
+pub trait GetClone<T>: Get<T> {
+    fn get_cloned(&self) -> T
+    where
+        T: Clone;
+}
+
+impl<T, M: Get<T> + ?Sized> GetClone<T> for M {
+    fn get_cloned(&self) -> T
+    where
+        T: Clone,
+    {
+        self.get().clone()
+    }
+}
+
+/// Helper trait to get by value a specific `Copy` type `T` from a tuple struct containing disjoint heterogeneous types
+///
+/// It is implemented for all types implementing [`Get<T>`].
+pub trait GetCopy<T: Copy>: Get<T> {
+    fn get_copy(&self) -> T;
+}
+
+impl<T: Copy, M: Get<T> + ?Sized> GetCopy<T> for M {
+    fn get_copy(&self) -> T {
+        *self.get()
+    }
+}
+
+/// Helper trait to get a specific type `T` from a tuple struct which might not contain it
+///
+/// It is implemented for all types implementing [`Get<T>`], and by the derive macro for all
+/// fields of type `Option<T>`.
+pub trait TryGet<T> {
+    fn try_get(&self) -> Option<&T>;
+}
+
+impl<T, M: Get<T>> TryGet<T> for M {
+    fn try_get(&self) -> Option<&T> {
+        Some(self.get())
+    }
+}
+
+/// Helper trait to mutably get a specific type `T` from a tuple struct which might not contain it
+///
+/// It is implemented for all types implementing [`GetMut<T>`], and by the derive macro for all
+/// fields of type `Option<T>` of mutable typemaps.
+pub trait TryGetMut<T> {
+    fn try_get_mut(&mut self) -> Option<&mut T>;
+}
+
+impl<T, M: GetMut<T>> TryGetMut<T> for M {
+    fn try_get_mut(&mut self) -> Option<&mut T> {
+        Some(self.get_mut())
+    }
+}
+
+/// Helper trait to assign a new value to a specific type `T` of a tuple struct containing disjoint heterogeneous types
+///
+/// It is implemented for all types implementing [`GetMut<T>`].
+pub trait Set<T> {
+    fn set(&mut self, value: T);
+}
+
+impl<T, M: GetMut<T>> Set<T> for M {
+    fn set(&mut self, value: T) {
+        *self.get_mut() = value;
+    }
+}
+
+/// Helper trait to assign a new value to a specific type `T` of a tuple struct containing disjoint heterogeneous types, returning the previous one
+///
+/// It is implemented for all types implementing [`GetMut<T>`].
+pub trait Replace<T> {
+    fn replace(&mut self, value: T) -> T;
+}
+
+impl<T, M: GetMut<T>> Replace<T> for M {
+    fn replace(&mut self, value: T) -> T {
+        core::mem::replace(self.get_mut(), value)
+    }
+}
+
+/// Helper trait to move a value of a specific type `T` out of a tuple struct containing an `Option<T>`, leaving `None`
+///
+/// It is implemented for all types implementing [`GetMut<Option<T>>`].
+pub trait Take<T> {
+    fn take(&mut self) -> Option<T>;
+}
+
+impl<T, M: GetMut<Option<T>>> Take<T> for M {
+    fn take(&mut self) -> Option<T> {
+        self.get_mut().take()
+    }
+}
+
+/// Exchange the values of a specific type `T` of two tuple structs containing it, which can be of different types
+pub fn swap<T, A: GetMut<T>, B: GetMut<T>>(a: &mut A, b: &mut B) {
+    core::mem::swap(a.get_mut(), b.get_mut());
+}
+
+/// Either a reference to a value of a tuple struct, or an owned default value, as returned by [`get_or_default`]
+pub enum OrDefault<'a, T> {
+    Borrowed(&'a T),
+    Owned(T),
+}
+
+impl<T> core::ops::Deref for OrDefault<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        match self {
+            OrDefault::Borrowed(value) => value,
+            OrDefault::Owned(value) => value,
+        }
+    }
+}
+
+/// Helper trait to read by value a specific volatile type `T` from a tuple struct containing disjoint heterogeneous types
+pub trait ReadVolatile<T: Copy> {
+    fn read_volatile(&self) -> T;
+}
+
+/// Helper trait to write by value a specific volatile type `T` to a tuple struct containing disjoint heterogeneous types
+pub trait WriteVolatile<T: Copy> {
+    fn write_volatile(&mut self, value: T);
+}
+
+/// Memory used by a value, for instance on the heap, as reported by [`MemUsage::mem_usage`]
+///
+/// Typemaps with `#[typemap(mem_usage)]` implement it by summing the usage of their fields.
+/// Note that fields whose type is a generic parameter of the typemap are always skipped.
+pub trait MemUsage {
+    fn mem_usage(&self) -> usize;
+}
+
+/// Stable hash of the ordered list of the type names of the fields of a typemap
+///
+/// It is implemented by the derive macro with `#[typemap(fingerprint)]`, using the 64-bit FNV-1a
+/// hash of the type names as written in the source, with normalized spacing. Hence, the same type
+/// written as `String` and `std::string::String` leads to different fingerprints.
+pub trait Fingerprint {
+    const FINGERPRINT: u64;
+}
+
+/// Umbrella trait of the typemaps, describing their fields for generic code
+///
+/// It is implemented by the derive macro with `#[typemap(types)]`. It is not implemented by
+/// default, as public typemaps may hold private types, which cannot appear in its associated
+/// type.
+pub trait Typemap {
+    /// Tuple of the types of all fields, in declaration order
+    type Types;
+
+    /// Number of fields
+    const LEN: usize;
+
+    /// Names of the types of all fields as given by [`core::any::type_name`], in declaration
+    /// order, hence `LEN` of them
+    fn type_names() -> impl ExactSizeIterator<Item = &'static str>;
+}
+
+/// JSON description of a typemap, implemented by the derive macro with the `manifest` feature
+///
+/// The description is of the form
+/// `{"name":"Test","fields":[{"index":0,"type":"i32","mutable":false,"volatile":false}]}`.
+#[cfg(feature = "manifest")]
+pub trait Manifest {
+    const MANIFEST: &'static str;
+}
+
+/// Convenience macro to get a specific type `$t` from a tuple struct `$s` containing disjoint heterogeneous types
+///
+/// Passing a value is fine, as [`get`] will add a reference to `$t` before calling [`Get`].
+#[macro_export]
+macro_rules! get {
+    ($s:expr, $t:ty) => {
+        $crate::Get::<$t>::get(&$s)
+    };
+}
+
+/// Convenience macro to get a clone of a specific type `$t` from a tuple struct `$s` containing disjoint heterogeneous types
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// # extern crate alloc;
+/// use alloc::rc::Rc;
+///
+/// #[derive(Typemap)]
+/// struct Test(Rc<str>, u32);
+///
+/// let t = Test("handle".into(), 1);
+/// let handle = get_cloned!(t, Rc<str>);
+/// assert_eq!(Rc::strong_count(&handle), 2);
+/// ```
+#[macro_export]
+macro_rules! get_cloned {
+    ($s:expr, $t:ty) => {
+        $crate::GetClone::<$t>::get_cloned(&$s)
+    };
+}
+
+/// Convenience macro to get by value a specific `Copy` type `$t` from a tuple struct `$s` containing disjoint heterogeneous types
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// #[derive(Clone, Copy, PartialEq, Debug)]
+/// enum Mode {
+///     Fast,
+///     Safe,
+/// }
+///
+/// #[derive(Typemap)]
+/// struct Test(u32, Mode);
+///
+/// let t = Test(1, Mode::Safe);
+/// assert_eq!(get_copy!(t, u32) + 1, 2);
+/// assert_eq!(get_copy!(t, Mode), Mode::Safe);
+/// ```
+#[macro_export]
+macro_rules! get_copy {
+    ($s:expr, $t:ty) => {
+        $crate::GetCopy::<$t>::get_copy(&$s)
+    };
+}
+
+/// Convenience macro to get a pinned mutable reference to a specific type `$t` from a pinned tuple struct `$s` containing disjoint heterogeneous types
+///
+/// As `$s` is reborrowed, it can be used again afterwards.
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// use core::future::Future;
+/// use core::pin::{pin, Pin};
+/// use core::task::{Context, Poll, Waker};
+///
+/// struct Ready(u32);
+///
+/// impl Future for Ready {
+///     type Output = u32;
+///     fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<u32> {
+///         Poll::Ready(self.0)
+///     }
+/// }
+///
+/// #[derive(Typemap)]
+/// #[typemap(mut)]
+/// #[typemap_pin]
+/// struct Tasks(Ready, &'static str);
+///
+/// let mut tasks = pin!(Tasks(Ready(1), "tasks"));
+/// let mut context = Context::from_waker(Waker::noop());
+/// assert_eq!(get_pin_mut!(tasks, Ready).poll(&mut context), Poll::Ready(1));
+/// assert_eq!(*get_pin_mut!(tasks, &str), "tasks");
+/// ```
+#[macro_export]
+macro_rules! get_pin_mut {
+    ($s:expr, $t:ty) => {
+        $crate::GetPinMut::<$t>::get_pin_mut(::core::pin::Pin::as_mut(&mut $s))
+    };
+}
+
+/// Convenience macro expanding to the `impl Trait` type of the typemaps containing the types `$t`
+///
+/// As macros cannot be expanded in bounds, it is meant to be used in argument position.
+/// With a leading `mut`, the types must also be accessible mutably.
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// struct Config(u32);
+///
+/// fn step(m: &mut requires!(mut i32; f32, Config)) {
+///     *get_mut!(*m, i32) += get!(m, Config).0 as i32;
+/// }
+///
+/// #[derive(Typemap)]
+/// #[typemap(mut)]
+/// struct Test(i32, f32, Config);
+///
+/// let mut t = Test(1, 2.0, Config(3));
+/// step(&mut t);
+/// assert_eq!(t.0, 4);
+/// ```
+#[macro_export]
+macro_rules! requires {
+    (mut $($m:ty),+ ; $($t:ty),+ $(,)?) => {
+        impl $($crate::Get<$m> + $crate::GetMut<$m> +)+ ?Sized $(+ $crate::Get<$t>)+
+    };
+    (mut $($m:ty),+ $(,)?) => {
+        impl $($crate::Get<$m> + $crate::GetMut<$m> +)+ ?Sized
+    };
+    ($($t:ty),+ $(,)?) => {
+        impl ?Sized $(+ $crate::Get<$t>)+
+    };
+}
+
+/// Convenience macro to mutably get two distinct types `$a` and `$b` from a tuple struct `$s` containing disjoint heterogeneous types at the same time
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// #[derive(Typemap)]
+/// #[typemap(mut, disjoint)]
+/// struct Test(i32, f32, &'static str);
+///
+/// let mut t = Test(1, 2.0, "a");
+/// let (i, f) = get_disjoint_mut!(t, i32, f32);
+/// *i += *f as i32;
+/// *f = 0.0;
+/// assert_eq!((t.0, t.1), (3, 0.0));
+/// ```
+#[macro_export]
+macro_rules! get_disjoint_mut {
+    ($s:expr, $a:ty, $b:ty) => {
+        $crate::GetDisjointMut::<$a, $b>::get_disjoint_mut(&mut $s)
+    };
+}
+
+/// Convenience macro to borrow several distinct types of a tuple struct `$s` containing disjoint heterogeneous types at the same time, some of them mutably
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// struct Config(u8);
+/// struct Db(Vec<u8>);
+///
+/// #[derive(Typemap)]
+/// #[typemap(query)]
+/// struct Test(Config, #[typemap(mut)] Db);
+///
+/// let mut t = Test(Config(3), Db(vec![]));
+/// let (cfg, db) = query!(t, &Config, &mut Db);
+/// db.0.push(cfg.0);
+/// assert_eq!(t.1 .0, [3]);
+/// ```
+/// Requesting the same type twice does not compile:
+#[cfg_attr(feature = "derive", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// #[derive(Typemap)]
+/// #[typemap(mut, query)]
+/// struct Test(i32, f32);
+///
+/// let mut t = Test(1, 2.0);
+/// let (a, b) = query!(t, &i32, &mut i32);
+/// ```
+#[macro_export]
+macro_rules! query {
+    ($s:expr, $($q:ty),+ $(,)?) => {
+        <($($q,)+) as $crate::Query<_>>::query(&mut $s)
+    };
+}
+
+/// Convenience macro to get the field at position `$i` of a struct `$s` containing disjoint heterogeneous types
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// #[derive(Typemap)]
+/// #[typemap(positional)]
+/// struct Test(i32, &'static str);
+///
+/// let t = Test(1, "a");
+/// assert_eq!((*get_at!(t, 0), *get_at!(t, 1)), (1, "a"));
+/// ```
+#[macro_export]
+macro_rules! get_at {
+    ($s:expr, $i:expr) => {
+        $crate::GetAt::<{ $i }>::get_at(&$s)
+    };
+}
+
+/// Convenience macro to get a tuple of references to several types `$t` of a tuple struct `$s` containing disjoint heterogeneous types
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// #[derive(Typemap)]
+/// struct Test(i32, f32, &'static str);
+///
+/// let t = Test(1, 2.0, "a");
+/// let (i, s) = get_many!(t, i32, &str);
+/// assert_eq!((*i, *s), (1, "a"));
+/// ```
+#[macro_export]
+macro_rules! get_many {
+    ($s:expr, $($t:ty),+ $(,)?) => {
+        ($($crate::Get::<$t>::get(&$s),)+)
+    };
+}
+
+/// Convenience macro to mutably get a specific type `$t` from a tuple struct `$s` containing disjoint heterogeneous types
+///
+/// Passing a value is fine, as [`get_mut`] will add a reference to `$t` before calling [`GetMut`].
+#[macro_export]
+macro_rules! get_mut {
+    ($s:expr, $t:ty) => {
+        $crate::GetMut::<$t>::get_mut(&mut $s)
+    };
+}
+
+/// Convenience macro to assign a value `$v` to a specific type of a tuple struct `$s` containing disjoint heterogeneous types
+///
+/// The type is inferred from the value, so literals might need a suffix.
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// #[derive(Typemap)]
+/// #[typemap(mut)]
+/// struct Test(i32, u8);
+///
+/// let mut t = Test(1, 2);
+/// set!(t, 3i32);
+/// set!(t, 4u8);
+/// assert_eq!((*get!(t, i32), *get!(t, u8)), (3, 4));
+/// ```
+#[macro_export]
+macro_rules! set {
+    ($s:expr, $v:expr) => {
+        $crate::Set::set(&mut $s, $v)
+    };
+}
+
+/// Convenience macro to assign a value `$v` to a specific type of a tuple struct `$s` containing disjoint heterogeneous types, returning the previous value
+///
+/// The type is inferred from the value, so literals might need a suffix.
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// #[derive(Typemap)]
+/// #[typemap(mut)]
+/// struct Test(i32, &'static str);
+///
+/// let mut t = Test(1, "old");
+/// assert_eq!(replace!(t, "new"), "old");
+/// assert_eq!(*get!(t, &str), "new");
+/// ```
+#[macro_export]
+macro_rules! replace {
+    ($s:expr, $v:expr) => {
+        $crate::Replace::replace(&mut $s, $v)
+    };
+}
+
+/// Convenience macro to move a value of a specific type `$t` out of a tuple struct `$s` containing an `Option<$t>`, leaving `None`
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// struct Connection;
+///
+/// #[derive(Typemap)]
+/// #[typemap(mut)]
+/// struct Test(Option<Connection>, u32);
+///
+/// let mut t = Test(Some(Connection), 1);
+/// assert!(take!(t, Connection).is_some());
+/// assert!(take!(t, Connection).is_none());
+/// ```
+#[macro_export]
+macro_rules! take {
+    ($s:expr, $t:ty) => {
+        $crate::Take::<$t>::take(&mut $s)
+    };
+}
+
+/// Convenience macro to exchange the values of a specific type `$t` of two tuple structs `$a` and `$b` containing it
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// #[derive(Typemap)]
+/// #[typemap(mut)]
+/// struct Buffers(Vec<u8>, u32);
+///
+/// let (mut front, mut back) = (Buffers(vec![1], 0), Buffers(vec![2], 1));
+/// swap!(front, back, Vec<u8>);
+/// assert_eq!((front.0, front.1), (vec![2], 0));
+/// ```
+#[macro_export]
+macro_rules! swap {
+    ($a:expr, $b:expr, $t:ty) => {
+        $crate::swap::<$t, _, _>(&mut $a, &mut $b)
+    };
+}
+
+/// Convenience macro to try to get a specific type `$t` from a tuple struct `$s`, which might not contain it
+///
+/// Unlike [`TryGet`], it also accepts tuple structs not implementing `TryGet<$t>`, returning `None`.
+/// As this is decided at compile time, the type of `$s` must be known, and not be a generic parameter.
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use typemap_meta::*;
+/// #[derive(Typemap)]
+/// struct Test(i32, Option<u8>);
+///
+/// let t = Test(1, Some(2));
+/// assert_eq!(try_get!(t, i32), Some(&1));
+/// assert_eq!(try_get!(t, u8), Some(&2));
+/// assert_eq!(try_get!(t, f32), None);
+/// ```
+#[macro_export]
+macro_rules! try_get {
+    ($s:expr, $t:ty) => {{
+        #[allow(unused_imports)]
+        use $crate::__private::{TryGetFallback, TryGetViaTryGet};
+        (&$crate::__private::TryGetProbe::<_, $t>::new(&$s)).try_get_probed()
+    }};
+}
+
+/// Convenience macro to get a specific type `$t` from a tuple struct `$s` implementing [`TryGet`], or its default value
+///
+/// The default value is only created if the tuple struct does not contain a value of type `$t`,
+/// and both cases are accessible through the [`OrDefault`] smart pointer.
+#[macro_export]
+macro_rules! get_or_default {
+    ($s:expr, $t:ty) => {
+        match $crate::TryGet::<$t>::try_get(&$s) {
+            ::core::option::Option::Some(value) => $crate::OrDefault::Borrowed(value),
+            ::core::option::Option::None => {
+                $crate::OrDefault::Owned(<$t as ::core::default::Default>::default())
+            }
+        }
+    };
+}
+
+/// Convenience macro to get a specific type `$t` from the first of several layers of tuple structs containing it
+///
+/// The layers are given from the most specific to the most general one, and the layer is selected
+/// at compile time. Compilation fails if the last layer does not contain `$t`. In generic code, a
+/// layer whose type is a type parameter only contains `$t` if its bounds require `Get<$t>`.
+#[macro_export]
+macro_rules! get_layered {
+    (@collect [$($layers:expr),*] $t:ty) => {
+        $crate::get_layered!(@resolve $t; $($layers),*)
+    };
+    (@collect [$($layers:expr),*] $layer:expr, $($rest:tt)+) => {
+        $crate::get_layered!(@collect [$($layers,)* $layer] $($rest)+)
+    };
+    (@resolve $t:ty; $last:expr) => {
+        $crate::Get::<$t>::get(&$last)
+    };
+    (@resolve $t:ty; $layer:expr, $($rest:expr),+) => {{
+        #[allow(unused_imports)]
+        use $crate::__private::{LayerFallback, LayerViaGet};
+        match (&$crate::__private::LayerProbe::<_, $t>::new(&$layer)).get_layer() {
+            ::core::option::Option::Some(value) => value,
+            ::core::option::Option::None => $crate::get_layered!(@resolve $t; $($rest),+),
+        }
+    }};
+    ($($input:tt)+) => {
+        $crate::get_layered!(@collect [] $($input)+)
+    };
+}
+
+/// Convenience macro to atomically load a specific type `$t` from a tuple struct `$s` containing an atomic of it
+///
+/// The ordering defaults to [`SeqCst`](core::sync::atomic::Ordering::SeqCst).
+#[macro_export]
+macro_rules! load {
+    ($s:expr, $t:ty) => {
+        $crate::load!($s, $t, ::core::sync::atomic::Ordering::SeqCst)
+    };
+    ($s:expr, $t:ty, $o:expr) => {
+        $crate::AtomicGet::<$t>::load(&$s, $o)
+    };
+}
+
+/// Convenience macro to atomically store a value `$v` of a specific type `$t` to a tuple struct `$s` containing an atomic of it
+///
+/// The ordering defaults to [`SeqCst`](core::sync::atomic::Ordering::SeqCst).
+#[macro_export]
+macro_rules! store {
+    ($s:expr, $t:ty, $v:expr) => {
+        $crate::store!($s, $t, $v, ::core::sync::atomic::Ordering::SeqCst)
+    };
+    ($s:expr, $t:ty, $v:expr, $o:expr) => {
+        $crate::AtomicGet::<$t>::store(&$s, $v, $o)
+    };
+}
+
+/// Convenience macro to asynchronously lock a specific type `$t` from a tuple struct `$s` containing an `embassy-sync` mutex of it
+///
+/// It evaluates to a future resolving to the guard of the mutex.
+#[cfg(feature = "embassy")]
+#[macro_export]
+macro_rules! lock {
+    ($s:expr, $t:ty) => {
+        $crate::MutexGet::<_, $t>::lock(&$s)
+    };
+}
+
+/// Convenience macro to get all values of a specific type `$t` from a tuple struct `$s` containing a collection of them
+#[macro_export]
+macro_rules! get_all {
+    ($s:expr, $t:ty) => {
+        $crate::GetAll::<$t>::get_all(&$s)
+    };
+}
+
+/// Convenience macro to mutably get all values of a specific type `$t` from a tuple struct `$s` containing a collection of them
+#[macro_export]
+macro_rules! get_all_mut {
+    ($s:expr, $t:ty) => {
+        $crate::GetAllMut::<$t>::get_all_mut(&mut $s)
+    };
+}
+
+/// Convenience macro to add a value `$v` of a specific type `$t` to a tuple struct `$s` containing a collection of them
+///
+/// It returns `Err($v)` if the collection is full.
+#[macro_export]
+macro_rules! push {
+    ($s:expr, $t:ty, $v:expr) => {
+        $crate::Push::<$t>::push(&mut $s, $v)
+    };
+}
+
+/// Convenience macro to read by value a specific volatile type `$t` from a tuple struct `$s` containing disjoint heterogeneous types
+#[macro_export]
+macro_rules! read_volatile {
+    ($s:expr, $t:ty) => {
+        $crate::ReadVolatile::<$t>::read_volatile(&$s)
+    };
+}
+
+/// Convenience macro to write by value `$v` to a specific volatile type `$t` of a tuple struct `$s` containing disjoint heterogeneous types
+#[macro_export]
+macro_rules! write_volatile {
+    ($s:expr, $t:ty, $v:expr) => {
+        $crate::WriteVolatile::<$t>::write_volatile(&mut $s, $v)
+    };
+}
+
+/// Implement [`Get`], and [`GetMut`] if preceded by `mut`, for the fields of a tuple struct,
+/// without using the derive macro
+///
+/// The types of the fields are listed in order, up to 16 of them, and the struct must not be generic.
+/// ```
+/// # use typemap_meta::*;
+/// struct Test(i32, f32);
+/// impl_typemap!(mut Test, i32, f32);
+///
+/// let mut t = Test(1, 2.0);
+/// *get_mut!(t, i32) += 1;
+/// assert_eq!(*get!(t, i32), 2);
+/// ```
+#[macro_export]
+macro_rules! impl_typemap {
+    (mut $name:ty, $($t:ty),+ $(,)?) => {
+        $crate::impl_typemap!(@get $name; [0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15]; $($t),+);
+        $crate::impl_typemap!(@get_mut $name; [0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15]; $($t),+);
+    };
+    ($name:ty, $($t:ty),+ $(,)?) => {
+        $crate::impl_typemap!(@get $name; [0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15]; $($t),+);
+    };
+    (@get $name:ty; [$index:tt $($indices:tt)*]; $t:ty $(, $rest:ty)*) => {
+        impl $crate::Get<$t> for $name {
+            fn get(&self) -> &$t {
+                &self.$index
+            }
+        }
+        $crate::impl_typemap!(@get $name; [$($indices)*]; $($rest),*);
+    };
+    (@get_mut $name:ty; [$index:tt $($indices:tt)*]; $t:ty $(, $rest:ty)*) => {
+        impl $crate::GetMut<$t> for $name {
+            fn get_mut(&mut self) -> &mut $t {
+                &mut self.$index
+            }
+        }
+        $crate::impl_typemap!(@get_mut $name; [$($indices)*]; $($rest),*);
+    };
+    (@$kind:ident $name:ty; [$($indices:tt)*]; ) => {};
+}
+
+/// Implementation details used by the code generated by the derive macro
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "env")]
+    pub use crate::env::{env_field, env_var};
+    #[cfg(feature = "bevy")]
+    pub use bevy_ecs;
+    #[cfg(feature = "embassy")]
+    pub use embassy_sync;
+    #[cfg(feature = "frunk")]
+    pub use frunk_core;
+    #[cfg(feature = "rayon")]
+    pub use rayon;
+    #[cfg(feature = "rhai")]
+    pub use rhai;
+    #[cfg(feature = "type-map")]
+    pub use type_map;
+    #[cfg(feature = "rhai")]
+    pub type RhaiResult<T> = Result<T, alloc::boxed::Box<rhai::EvalAltResult>>;
+    #[cfg(feature = "rhai")]
+    extern crate alloc;
+    pub use crate::query::distinct as distinct_indices;
+    #[cfg(feature = "serde")]
+    pub use serde;
+
+    /// Lookup of the field of type `Self::Value` of sealed typemap `M`, implemented on `PhantomData`
+    pub trait SealedGet<M: ?Sized> {
+        type Value;
+        fn get(map: &M) -> &Self::Value;
+    }
+
+    /// Mutable lookup of the field of type `Self::Value` of sealed typemap `M`, implemented on `PhantomData`
+    pub trait SealedGetMut<M: ?Sized>: SealedGet<M> {
+        fn get_mut(map: &mut M) -> &mut Self::Value;
+    }
+
+    /// Sealed typemap defined in this crate, for checking that other crates cannot implement
+    /// `Get` on it
+    #[cfg(feature = "derive")]
+    #[derive(crate::Typemap)]
+    #[typemap(crate = "crate", sealed)]
+    pub struct SealedExample(pub i32);
+
+    /// Wrapper of a pinned field type, which is `Unpin` if the type is, used to express this
+    /// requirement with a lifetime, so that it is not rejected as a trivial bound
+    pub struct PinnedField<'a, T: ?Sized>(core::marker::PhantomData<&'a ()>, T);
+
+    /// Autoref-based specialization probe to query the memory usage of types implementing `MemUsage`
+    pub struct MemUsageProbe<'a, T>(pub &'a T);
+
+    pub trait MemUsageViaTrait {
+        fn mem_usage(&self) -> Option<usize>;
+    }
+
+    impl<T: crate::MemUsage> MemUsageViaTrait for MemUsageProbe<'_, T> {
+        fn mem_usage(&self) -> Option<usize> {
+            Some(self.0.mem_usage())
+        }
+    }
+
+    pub trait MemUsageFallback {
+        fn mem_usage(&self) -> Option<usize>;
+    }
+
+    impl<T> MemUsageFallback for &MemUsageProbe<'_, T> {
+        fn mem_usage(&self) -> Option<usize> {
+            None
+        }
+    }
+
+    /// Autoref-based specialization probe to get a `T` from a layer of `get_layered!`, if it contains one
+    pub struct LayerProbe<'a, L, T>(pub &'a L, pub core::marker::PhantomData<T>);
+
+    impl<'a, L, T> LayerProbe<'a, L, T> {
+        pub fn new(layer: &'a L) -> Self {
+            Self(layer, core::marker::PhantomData)
+        }
+    }
+
+    pub trait LayerViaGet<'a, T> {
+        fn get_layer(&self) -> Option<&'a T>;
+    }
+
+    impl<'a, L: crate::Get<T>, T> LayerViaGet<'a, T> for LayerProbe<'a, L, T> {
+        fn get_layer(&self) -> Option<&'a T> {
+            Some(self.0.get())
+        }
+    }
+
+    pub trait LayerFallback<'a, T> {
+        fn get_layer(&self) -> Option<&'a T>;
+    }
+
+    impl<'a, L, T> LayerFallback<'a, T> for &LayerProbe<'a, L, T> {
+        fn get_layer(&self) -> Option<&'a T> {
+            None
+        }
+    }
+
+    /// Autoref-based specialization probe to try to get a `T` from a typemap in `try_get!`, even if it does not implement `TryGet<T>`
+    pub struct TryGetProbe<'a, M, T>(pub &'a M, pub core::marker::PhantomData<T>);
+
+    impl<'a, M, T> TryGetProbe<'a, M, T> {
+        pub fn new(map: &'a M) -> Self {
+            Self(map, core::marker::PhantomData)
+        }
+    }
+
+    pub trait TryGetViaTryGet<'a, T> {
+        fn try_get_probed(&self) -> Option<&'a T>;
+    }
+
+    impl<'a, M: crate::TryGet<T>, T> TryGetViaTryGet<'a, T> for TryGetProbe<'a, M, T> {
+        fn try_get_probed(&self) -> Option<&'a T> {
+            self.0.try_get()
+        }
+    }
+
+    pub trait TryGetFallback<'a, T> {
+        fn try_get_probed(&self) -> Option<&'a T>;
+    }
+
+    impl<'a, M, T> TryGetFallback<'a, T> for &TryGetProbe<'a, M, T> {
+        fn try_get_probed(&self) -> Option<&'a T> {
+            None
+        }
+    }
+
+    /// Write `: value` for the field of a displayed typemap if it implements `Display`, nothing otherwise
+    pub struct DisplayProbe<'a, T: ?Sized>(pub &'a T);
+
+    pub trait DisplayViaDisplay {
+        fn fmt_value(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result;
+    }
+
+    impl<T: core::fmt::Display + ?Sized> DisplayViaDisplay for DisplayProbe<'_, T> {
+        fn fmt_value(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, ": {}", self.0)
+        }
+    }
+
+    pub trait DisplayFallback {
+        fn fmt_value(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result;
+    }
+
+    impl<T: ?Sized> DisplayFallback for &DisplayProbe<'_, T> {
+        fn fmt_value(&self, _f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            Ok(())
+        }
+    }
+
+    /// Return `max_size` if `size` is within it, and `size` otherwise
+    pub const fn size_within(size: usize, max_size: usize) -> usize {
+        if size > max_size {
+            size
+        } else {
+            max_size
+        }
+    }
+
+    pub fn read_volatile<T: Copy>(src: &T) -> T {
+        // SAFETY: src is a reference, hence valid for reads and properly aligned
+        unsafe { core::ptr::read_volatile(src) }
+    }
+
+    pub fn write_volatile<T: Copy>(dst: &mut T, value: T) {
+        // SAFETY: dst is a mutable reference, hence valid for writes and properly aligned
+        unsafe { core::ptr::write_volatile(dst, value) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "manifest")]
+    use crate::Manifest;
+    #[cfg(feature = "derive")]
+    use crate::{AsBytes, Fingerprint, MemUsage, TryGet, TryGetMut};
+    use crate::{Get, GetMut};
+
+    // without using the generation macro
+
+    #[test]
+    fn impl_get() {
+        struct Test(i32, f32);
+        impl Get<i32> for Test {
+            fn get(&self) -> &i32 {
+                &self.0
+            }
+        }
+        impl Get<f32> for Test {
+            fn get(&self) -> &f32 {
+                &self.1
+            }
+        }
+        let t = Test(1, 2.0);
+        assert_eq!(*get!(t, i32), 1);
+        assert_eq!(*get!(t, f32), 2.0);
+    }
+
+    #[test]
+    fn impl_get_mut() {
+        struct Test(i32, f32);
+        impl Get<i32> for Test {
+            fn get(&self) -> &i32 {
+                &self.0
+            }
+        }
+        impl Get<f32> for Test {
+            fn get(&self) -> &f32 {
+                &self.1
+            }
+        }
+
+        impl GetMut<i32> for Test {
+            fn get_mut(&mut self) -> &mut i32 {
+                &mut self.0
+            }
+        }
+        impl GetMut<f32> for Test {
+            fn get_mut(&mut self) -> &mut f32 {
+                &mut self.1
+            }
+        }
+
+        let t = Test(1, 2.0);
+        assert_eq!(*get!(t, i32), 1);
+        assert_eq!(*get!(t, f32), 2.0);
+
+        let mut t = t;
+
+        *get_mut!(t, i32) = 3;
+        *get_mut!(t, f32) = 4.0;
+        assert_eq!(*get!(t, i32), 3);
+        assert_eq!(*get!(t, f32), 4.0);
+    }
+
+    #[test]
+    fn impl_get_ref() {
+        struct Test<'a>(&'a i32, &'a f32);
+        impl<'a> Get<&'a i32> for Test<'a> {
+            fn get(&self) -> &&'a i32 {
+                &self.0
+            }
+        }
+        impl<'a> Get<&'a f32> for Test<'a> {
+            fn get(&self) -> &&'a f32 {
+                &self.1
+            }
+        }
+        let t = Test(&1, &2.0);
+        assert_eq!(**get!(t, &i32), 1);
+        assert_eq!(**get!(t, &f32), 2.0);
+    }
+
+    #[test]
+    fn impl_get_mut_ref() {
+        struct Test<'a>(&'a i32, &'a f32);
+        impl<'a> Get<&'a i32> for Test<'a> {
+            fn get(&self) -> &&'a i32 {
+                &self.0
+            }
+        }
+        impl<'a> Get<&'a f32> for Test<'a> {
+            fn get(&self) -> &&'a f32 {
+                &self.1
+            }
+        }
+
+        impl<'a> GetMut<&'a i32> for Test<'a> {
+            fn get_mut(&mut self) -> &mut &'a i32 {
+                &mut self.0
+            }
+        }
+        impl<'a> GetMut<&'a f32> for Test<'a> {
+            fn get_mut(&mut self) -> &mut &'a f32 {
+                &mut self.1
+            }
+        }
+        let t = Test(&1, &2.0);
+        assert_eq!(**get!(t, &i32), 1);
+        assert_eq!(**get!(t, &f32), 2.0);
+
+        let mut t = t;
+
+        *get_mut!(t, &i32) = &3;
+        *get_mut!(t, &f32) = &4.0;
+        assert_eq!(**get!(t, &i32), 3);
+        assert_eq!(**get!(t, &f32), 4.0);
+    }
+
+    // with using the generation macro
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_scalar() {
+        extern crate std;
+        use std::marker::PhantomData;
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        struct A<T> {
+            _f: PhantomData<T>,
+        }
+        #[derive(crate::Typemap)]
+        struct Test(i32, f32, A<u32>);
+        let a = A { _f: PhantomData };
+        let t = Test(1, 2.0, a);
+        assert_eq!(*get!(t, i32), 1);
+        assert_eq!(*get!(t, f32), 2.0);
+        assert_eq!(*get!(t, A<u32>), a);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_scalar_mut() {
+        extern crate std;
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        struct A<T>(T);
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Test(i32, f32, A<u32>);
+        let a = A(3);
+        let t = Test(1, 2.0, a);
+        assert_eq!(*get!(t, i32), 1);
+        assert_eq!(*get!(t, f32), 2.0);
+        assert_eq!(*get!(t, A<u32>), a);
+
+        let mut t = t;
+        let b = A(6);
+
+        *get_mut!(t, i32) = 4;
+        *get_mut!(t, f32) = 5.0;
+        *get_mut!(t, A<u32>) = b;
+
+        assert_eq!(*get!(t, i32), 4);
+        assert_eq!(*get!(t, f32), 5.0);
+        assert_eq!(*get!(t, A<u32>), b);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_struct() {
+        #[derive(Debug, PartialEq)]
+        struct A {}
+        #[derive(Debug, PartialEq)]
+        struct B {}
+        #[derive(crate::Typemap)]
+        struct Test(A, B);
+        let t = Test(A {}, B {});
+        assert_eq!(*get!(t, A), A {});
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_struct_mut() {
+        #[derive(Debug, PartialEq)]
+        struct A {
+            x: u32,
+        }
+        #[derive(Debug, PartialEq)]
+        struct B {}
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Test(A, B);
+        let t = Test(A { x: 1 }, B {});
+        assert_eq!(*get!(t, A), A { x: 1 });
+
+        let mut t = t;
+
+        get_mut!(t, A).x = 2;
+        assert_eq!(*get!(t, A), A { x: 2 });
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_ref() {
+        #[derive(Debug, PartialEq)]
+        struct A {}
+        #[derive(Debug, PartialEq)]
+        struct B {}
+        #[derive(crate::Typemap)]
+        struct Test<'l>(&'l A, &'l B, i32, f32);
+        let a = A {};
+        let b = B {};
+        let t = Test(&a, &b, 1, 2.0);
+        assert_eq!(**get!(t, &A), A {});
+        assert_eq!(**get!(t, &B), B {});
+        assert_eq!(*get!(t, i32), 1);
+        assert_eq!(*get!(t, f32), 2.0);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_ref_mut() {
+        #[derive(Debug, PartialEq)]
+        struct A {
+            x: u32,
+        }
+        #[derive(Debug, PartialEq)]
+        struct B {}
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Test<'l>(&'l A, &'l B, i32, f32);
+        let a = A { x: 1 };
+        let b = B {};
+        let t = Test(&a, &b, 1, 2.0);
+        assert_eq!(**get!(t, &A), A { x: 1 });
+        assert_eq!(**get!(t, &B), B {});
+        assert_eq!(*get!(t, i32), 1);
+        assert_eq!(*get!(t, f32), 2.0);
+
+        let mut t = t;
+
+        *get_mut!(t, &A) = &A { x: 2 };
+        *get_mut!(t, &B) = &B {};
+        *get_mut!(t, i32) = 3;
+        *get_mut!(t, f32) = 4.0;
+        assert_eq!(**get!(t, &A), A { x: 2 });
+        assert_eq!(**get!(t, &B), B {});
+        assert_eq!(*get!(t, i32), 3);
+        assert_eq!(*get!(t, f32), 4.0);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_box_trait() {
+        extern crate std;
+        use std::{boxed::Box, fmt::Debug};
+        // trait TA and struct A
+        trait TA {
+            fn value_a(&self) -> i32;
+        }
+        #[derive(Debug, PartialEq)]
+        struct A {
+            v: i32,
+        }
+        impl TA for A {
+            fn value_a(&self) -> i32 {
+                self.v
+            }
+        }
+        // trait TB and struct B
+        trait TB {
+            fn value_b(&self) -> f32;
+        }
+        #[derive(Debug, PartialEq)]
+        struct B {
+            v: f32,
+        }
+        impl TB for B {
+            fn value_b(&self) -> f32 {
+                self.v
+            }
+        }
+        // instance and asserts
+        #[derive(crate::Typemap)]
+        struct Test(Box<dyn TA>, Box<dyn TB>);
+        let t = Test(Box::new(A { v: 1 }), Box::new(B { v: 2.0 }));
+        assert_eq!(get!(t, Box<dyn TA>).value_a(), 1);
+        assert_eq!(get!(t, Box<dyn TB>).value_b(), 2.0);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_box_trait_mut() {
+        extern crate std;
+        use std::{boxed::Box, fmt::Debug};
+        // trait TA and struct A
+        trait TA {
+            fn value_a(&self) -> i32;
+            fn set_a(&mut self, a: i32);
+        }
+        #[derive(Debug, PartialEq)]
+        struct A {
+            v: i32,
+        }
+        impl TA for A {
+            fn value_a(&self) -> i32 {
+                self.v
+            }
+            fn set_a(&mut self, a: i32) {
+                self.v = a;
+            }
+        }
+        // trait TB and struct B
+        trait TB {
+            fn value_b(&self) -> f32;
+            fn set_b(&mut self, b: f32);
+        }
+        #[derive(Debug, PartialEq)]
+        struct B {
+            v: f32,
+        }
+        impl TB for B {
+            fn value_b(&self) -> f32 {
+                self.v
+            }
+            fn set_b(&mut self, b: f32) {
+                self.v = b;
+            }
+        }
+        // instance and asserts
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Test(Box<dyn TA>, Box<dyn TB>);
+        let t = Test(Box::new(A { v: 1 }), Box::new(B { v: 2.0 }));
+        assert_eq!(get!(t, Box<dyn TA>).value_a(), 1);
+        assert_eq!(get!(t, Box<dyn TB>).value_b(), 2.0);
+
+        let mut t = t;
+        get_mut!(t, Box<dyn TA>).set_a(3);
+        get_mut!(t, Box<dyn TB>).set_b(4.0);
+        assert_eq!(get!(t, Box<dyn TA>).value_a(), 3);
+        assert_eq!(get!(t, Box<dyn TB>).value_b(), 4.0);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_static_trait() {
+        extern crate std;
+        use std::fmt::Debug;
+        // trait TA and struct A
+        trait TA {
+            fn value_a(&self) -> i32;
+        }
+        #[derive(Debug, PartialEq)]
+        struct A {
+            v: i32,
+        }
+        impl TA for A {
+            fn value_a(&self) -> i32 {
+                self.v
+            }
+        }
+        // trait TB and struct B
+        trait TB {
+            fn value_b(&self) -> f32;
+        }
+        #[derive(Debug, PartialEq)]
+        struct B {
+            v: f32,
+        }
+        impl TB for B {
+            fn value_b(&self) -> f32 {
+                self.v
+            }
+        }
+        // instance and asserts
+        #[derive(crate::Typemap)]
+        struct Test(&'static dyn TA, &'static dyn TB);
+        let a: &'static dyn TA = &A { v: 1 };
+        let b: &'static dyn TB = &B { v: 2.0 };
+        let t = Test(a, b);
+        assert_eq!(get!(t, &'static dyn TA).value_a(), 1);
+        assert_eq!(get!(t, &'static dyn TB).value_b(), 2.0);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_static_trait_mut() {
+        extern crate std;
+        use std::fmt::Debug;
+        // trait TA and struct A
+        trait TA {
+            fn value_a(&self) -> i32;
+        }
+        #[derive(Debug, PartialEq)]
+        struct A {
+            v: i32,
+        }
+        impl TA for A {
+            fn value_a(&self) -> i32 {
+                self.v
+            }
+        }
+        // trait TB and struct B
+        trait TB {
+            fn value_b(&self) -> f32;
+        }
+        #[derive(Debug, PartialEq)]
+        struct B {
+            v: f32,
+        }
+        impl TB for B {
+            fn value_b(&self) -> f32 {
+                self.v
+            }
+        }
+        // instance and asserts
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Test(&'static dyn TA, &'static dyn TB);
+        let a: &'static dyn TA = &A { v: 1 };
+        let b: &'static dyn TB = &B { v: 2.0 };
+        let t = Test(a, b);
+        assert_eq!(get!(t, &'static dyn TA).value_a(), 1);
+        assert_eq!(get!(t, &'static dyn TB).value_b(), 2.0);
+
+        let mut t = t;
+        let c: &'static dyn TA = &A { v: 3 };
+        let d: &'static dyn TB = &B { v: 4.0 };
+
+        *get_mut!(t, &'static dyn TA) = c;
+        *get_mut!(t, &'static dyn TB) = d;
+
+        assert_eq!(get!(t, &'static dyn TA).value_a(), 3);
+        assert_eq!(get!(t, &'static dyn TB).value_b(), 4.0);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_named() {
+        #[derive(crate::Typemap, Clone, Debug, PartialEq)]
+        #[typemap(mut, extract, reset, cell)]
+        struct Test {
+            count: u32,
+            limit: Option<u8>,
+            #[typemap(skip)]
+            scale: u16,
+        }
+        let mut t = Test {
+            count: 1,
+            limit: Some(2),
+            scale: 3,
+        };
+        *get_mut!(t, u32) += 1;
+        assert_eq!(*get!(t, Option<u8>), Some(2));
+        assert_eq!(TryGet::<u8>::try_get(&t), Some(&2));
+
+        assert_eq!(Test::extracted_from(&t).scale, 0);
+        assert_eq!(Test::from(TestCell::from(t.clone())), t);
+        t.reset();
+        assert_eq!((t.count, t.limit, t.scale), (0, None, 0));
+
+        #[derive(crate::Typemap, Debug, PartialEq)]
+        #[typemap(bytes)]
+        struct Packed {
+            a: u16,
+            b: [u8; 2],
+        }
+        let p = Packed { a: 1, b: [2, 3] };
+        let mut bytes = [0; <Packed as AsBytes>::SIZE];
+        p.to_bytes(&mut bytes);
+        assert_eq!(Packed::from_bytes(&bytes), p);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn typemap_fields() {
+        #[crate::typemap_fields(count, limit)]
+        struct Test<'a> {
+            name: &'a str,
+            count: u32,
+            limit: Option<u8>,
+            total: u32,
+        }
+        let t = Test {
+            name: "test",
+            count: 1,
+            limit: Some(2),
+            total: 3,
+        };
+        assert_eq!(*get!(t, u32), 1);
+        assert_eq!(*get!(t, Option<u8>), Some(2));
+        assert_eq!((t.name, t.total), ("test", 3));
+
+        use crate::attribute::typemap;
+        #[typemap(fields(name, limit))]
+        struct Selected<'a> {
+            name: &'a str,
+            count: u32,
+            limit: Option<u8>,
+        }
+        let s = Selected {
+            name: "selected",
+            count: 1,
+            limit: None,
+        };
+        assert_eq!((*get!(s, &str), *get!(s, Option<u8>)), ("selected", None));
+        assert_eq!(s.count, 1);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_enum() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        enum Test<'a> {
+            Name(&'a str),
+            Count(u32),
+            None,
+        }
+        let mut t = Test::Count(1);
+        *TryGetMut::<u32>::try_get_mut(&mut t).unwrap() += 1;
+        assert_eq!(TryGet::<u32>::try_get(&t), Some(&2));
+        assert_eq!(TryGet::<&str>::try_get(&t), None);
+        let t = Test::Name("name");
+        assert_eq!(TryGet::<&str>::try_get(&t), Some(&"name"));
+        assert_eq!(*get_or_default!(Test::None, u32), 0);
+
+        #[derive(crate::Typemap)]
+        enum Single {
+            Only(i32),
+        }
+        assert_eq!(TryGet::<i32>::try_get(&Single::Only(1)), Some(&1));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_bounded_generics() {
+        use crate::Commands;
+        #[derive(crate::Typemap)]
+        #[typemap(mut, extract, reset, display, cell, commands(Clone))]
+        struct Test<T: Copy + Default, U>([T; 1], (U, u8))
+        where
+            U: Clone + Default;
+        let mut t = Test([1u32], ((), 2));
+        get_mut!(t, [u32; 1])[0] += 1;
+        t.apply(TestCommand::Field1(((), 3)));
+        assert_eq!(*get!(Test::extracted_from(&t), [u32; 1]), [2]);
+        assert_eq!(*get!(t, ((), u8)), ((), 3));
+        let t = Test::from(TestCell::from(t));
+        assert_eq!(t.1 .1, 3);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_generic_instantiations() {
+        // a parameter cannot be instantiated with a type containing it
+        #[derive(crate::Typemap)]
+        struct Nested<T>(T, [T; 1]);
+        let n = Nested(1u8, [2]);
+        assert_eq!((*get!(n, u8), *get!(n, [u8; 1])), (1, [2]));
+
+        #[derive(crate::Typemap)]
+        struct Registers<T>(T, #[typemap(volatile)] u16);
+        let r = Registers(1u8, 2);
+        assert_eq!(read_volatile!(r, u16), 2);
+        let r = Registers(1u16, 2);
+        assert_eq!((*get!(r, u16), read_volatile!(r, u16)), (1, 2));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_const_generics() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut, reset, cell, fingerprint)]
+        struct Test<const N: usize>([u8; N], u32);
+        let mut t = Test([1; 4], 2);
+        get_mut!(t, [u8; 4])[0] = 3;
+        assert_eq!(*get!(t, [u8; 4]), [3, 1, 1, 1]);
+        let t = Test::from(TestCell::from(t));
+        assert_eq!(*get!(t, u32), 2);
+        assert_eq!(Test::<2>::FINGERPRINT, Test::<4>::FINGERPRINT);
+        let mut t = Test([1; 2], 2);
+        t.reset();
+        assert_eq!((t.0, t.1), ([0; 2], 0));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_cfg_fields() {
+        // fields disabled by `cfg` are removed before the derive macro sees the struct
+        #[derive(crate::Typemap)]
+        #[typemap(mut, reset, cell, display)]
+        struct Test(u32, #[cfg(any())] NotExisting, #[cfg(test)] Option<u8>);
+        let mut t = Test(1, Some(2));
+        assert_eq!(*get!(t, Option<u8>), Some(2));
+        t.reset();
+        assert_eq!(*get!(t, u32), 0);
+        let _ = TestCell::from(t);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_field_mutability() {
+        use crate::AnyTypemap;
+
+        #[derive(crate::Typemap)]
+        struct Test(#[typemap_mut] u32, #[typemap(mut)] Option<u8>, i8);
+        let mut t = Test(1, None, 2);
+        *get_mut!(t, u32) += 1;
+        assert_eq!(TryGetMut::<u8>::try_get_mut(&mut t), None);
+        assert_eq!((t.0, t.1, t.2), (2, None, 2));
+
+        #[derive(crate::Typemap)]
+        #[typemap(mut, sealed, any)]
+        struct Sealed(u32, #[typemap(readonly)] i8);
+        let mut s = Sealed(1, 2);
+        *get_mut!(s, u32) += 1;
+        assert_eq!(s.iter_any_mut().count(), 1);
+        assert_eq!(s.lookup_mut::<i8>(), None);
+        assert_eq!(*get!(s, i8), 2);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn set() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Test(i32, &'static str, Option<u8>);
+        let mut t = Test(1, "a", None);
+        set!(t, 2i32);
+        set!(t, "b");
+        set!(t, Some(3u8));
+        assert_eq!((t.0, t.1, t.2), (2, "b", Some(3)));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn replace() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Test(i32, Option<u8>);
+        let mut t = Test(1, None);
+        assert_eq!(replace!(t, 2i32), 1);
+        assert_eq!(replace!(t, Some(3u8)), None);
+        assert_eq!(crate::Replace::<Option<u8>>::replace(&mut t, None), Some(3));
+        assert_eq!((t.0, t.1), (2, None));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn take() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Test(Option<i32>, Option<&'static str>, u8);
+        let mut t = Test(Some(1), None, 2);
+        assert_eq!(take!(t, i32), Some(1));
+        assert_eq!(take!(t, i32), None);
+        assert_eq!(take!(t, &str), None);
+        assert_eq!((t.0, t.2), (None, 2));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn swap() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Front(i32, u8);
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Back(u8, f32);
+        let (mut front, mut back) = (Front(1, 2), Back(3, 4.0));
+        swap!(front, back, u8);
+        assert_eq!((front.0, front.1, back.0, back.1), (1, 3, 2, 4.0));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn try_get() {
+        #[derive(crate::Typemap)]
+        struct Test(i32, Option<u8>, Option<&'static str>);
+        let t = Test(1, None, Some("a"));
+        assert_eq!(try_get!(t, i32), Some(&1));
+        assert_eq!(try_get!(t, u8), None);
+        assert_eq!(try_get!(t, &str), Some(&"a"));
+        assert_eq!(try_get!(t, f32), None);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn has() {
+        use crate::Has;
+        fn sum<M: Has<i32> + Has<u8>>(m: &M) -> i32 {
+            *get!(*m, i32) + i32::from(*get!(*m, u8))
+        }
+        #[derive(crate::Typemap)]
+        struct Test(i32, u8, f32);
+        assert_eq!(sum(&Test(1, 2, 3.0)), 3);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn get_cloned() {
+        #[derive(crate::Typemap)]
+        struct Test(i32, [u8; 2]);
+        let t = Test(1, [2, 3]);
+        assert_eq!(get_cloned!(t, i32), 1);
+        assert_eq!(get_cloned!(t, [u8; 2]), [2, 3]);
+        assert_eq!(crate::GetClone::<[u8; 2]>::get_cloned(&t), [2, 3]);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn get_copy() {
+        #[derive(crate::Typemap)]
+        struct Test(u32, f32, &'static str);
+        let t = Test(1, 2.0, "a");
+        assert_eq!(get_copy!(t, u32) + get_copy!(t, f32) as u32, 3);
+        assert_eq!(get_copy!(t, &str), "a");
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_put() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut, put)]
+        struct Test<T>([T; 1], &'static str, #[typemap(readonly)] u8);
+        let mut t = Test([1], "a", 2);
+        t.put([3]).put("b");
+        assert_eq!((t.0, t.1, t.2), ([3], "b", 2));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_asref() {
+        #[derive(crate::Typemap)]
+        #[typemap_asref]
+        #[typemap(mut)]
+        struct Test<T>([T; 1], #[typemap(readonly)] u8);
+        #[derive(crate::Typemap)]
+        #[typemap(asref)]
+        struct Options(i32);
+        let mut t = Test([1], 2);
+        AsMut::<[i32; 1]>::as_mut(&mut t)[0] += 1;
+        assert_eq!(
+            (*AsRef::<[i32; 1]>::as_ref(&t), *AsRef::<u8>::as_ref(&t)),
+            ([2], 2)
+        );
+        assert_eq!(*Options(3).as_ref(), 3);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_borrow() {
+        use core::borrow::{Borrow, BorrowMut};
+        #[derive(crate::Typemap)]
+        #[typemap_borrow]
+        #[typemap(mut)]
+        struct Test<T>([T; 1], #[typemap(readonly)] u8);
+        #[derive(crate::Typemap)]
+        #[typemap(borrow)]
+        struct Options(i32);
+        fn get_u8(map: &impl Borrow<u8>) -> u8 {
+            *map.borrow()
+        }
+        let mut t = Test([1], 2);
+        BorrowMut::<[i32; 1]>::borrow_mut(&mut t)[0] += 1;
+        assert_eq!((t.0, get_u8(&t)), ([2], 2));
+        assert_eq!(*Borrow::<i32>::borrow(&Options(3)), 3);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_into_ref() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut, into_ref)]
+        struct Test<'a, T>([T; 1], &'a str, #[typemap(readonly)] u8);
+        #[derive(crate::Typemap)]
+        #[typemap(into_ref)]
+        struct Generic<T>(T);
+        let mut t = Test([2], "a", 3);
+        let array: &mut [i32; 1] = (&mut t).into();
+        array[0] += 1;
+        let (array, str, u8): (&[i32; 1], &&str, &u8) = ((&t).into(), (&t).into(), (&t).into());
+        assert_eq!((array, *str, *u8), (&[3], "a", 3));
+        assert_eq!(*get!(Generic(4), i32), 4);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_tuple() {
+        #[derive(crate::Typemap)]
+        #[typemap(tuple)]
+        struct Test<T>([T; 1], u8);
+        #[derive(crate::Typemap)]
+        #[typemap(tuple)]
+        struct Named {
+            count: i32,
+            #[typemap(skip)]
+            other: i32,
+        }
+        #[derive(crate::Typemap)]
+        #[typemap(tuple)]
+        struct Single(f32);
+        let t = Test::from(([1], 2));
+        assert_eq!(<(_, _)>::from(t), ([1], 2));
+        let n = Named::from((3, 4));
+        assert_eq!((n.count, n.other), (3, 4));
+        assert_eq!(<(i32, i32)>::from(n), (3, 4));
+        assert_eq!(<(f32,)>::from(Single::from((5.0,))), (5.0,));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_into() {
+        use core::marker::PhantomData;
+        #[derive(crate::Typemap)]
+        #[typemap(into(Target, Other))]
+        struct Source(u8, &'static str, #[typemap(skip)] f32);
+        #[derive(crate::Typemap)]
+        struct Target(Option<&'static str>, u16, PhantomData<i8>);
+        #[derive(crate::Typemap)]
+        struct Other {
+            name: &'static str,
+            byte: u8,
+        }
+        let s = Source(1, "name", 3.0);
+        assert_eq!(s.2, 3.0);
+        let t = Target::from(s);
+        assert_eq!((t.0, t.1), (Some("name"), 1));
+        let o = Other::from(Source(4, "other", 6.0));
+        assert_eq!((o.name, o.byte), ("other", 4));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_inherent() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut, inherent)]
+        struct Test<T>([T; 1], #[typemap(readonly)] u8);
+        #[derive(crate::Typemap)]
+        #[typemap(inherent)]
+        struct Options(i32);
+        let mut t = Test([1], 2);
+        t.get_mut::<[i32; 1]>()[0] += 1;
+        assert_eq!((t.get::<[i32; 1]>(), t.get::<u8>()), (&[2], &2));
+        assert_eq!(*Options(3).get::<i32>(), 3);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_get_at() {
+        use crate::GetAt;
+        fn first<M: GetAt<0>>(m: &M) -> &M::Output {
+            m.get_at()
+        }
+        #[derive(crate::Typemap)]
+        #[typemap(positional)]
+        struct Test<T>(T, #[typemap(skip)] u8, #[typemap(volatile)] u16);
+        #[derive(crate::Typemap)]
+        #[typemap(positional)]
+        struct Named {
+            count: i32,
+            ratio: f32,
+        }
+        let t = Test([1], 2, 3);
+        assert_eq!((first(&t), t.1), (&[1], 2));
+        assert_eq!(read_volatile!(t, u16), 3);
+        let n = Named {
+            count: 4,
+            ratio: 5.0,
+        };
+        assert_eq!((*get_at!(n, 0), *get_at!(n, 1)), (4, 5.0));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_pin() {
+        use core::marker::PhantomPinned;
+        fn is_unpin<T: Unpin>(_: &T) {}
+        #[derive(crate::Typemap)]
+        #[typemap(mut, pin)]
+        struct Test<T>([T; 1], #[typemap(readonly)] PhantomPinned);
+        #[derive(crate::Typemap)]
+        #[typemap_pin]
+        #[typemap(mut)]
+        struct Pinned(PhantomPinned, u8);
+        let mut t = core::pin::pin!(Test([1], PhantomPinned));
+        get_pin_mut!(t, [i32; 1])[0] += 1;
+        is_unpin(&*t);
+        assert_eq!(t.0, [2]);
+        let mut p = core::pin::pin!(Pinned(PhantomPinned, 3));
+        *get_pin_mut!(p, u8) += 1;
+        let _: core::pin::Pin<&mut PhantomPinned> = get_pin_mut!(p, PhantomPinned);
+        assert_eq!(p.1, 4);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn refs() {
+        use crate::{Get, GetMut};
+        fn get_i32<M: Get<i32>>(m: M) -> i32 {
+            *m.get()
+        }
+        fn increment<M: GetMut<i32>>(mut m: M) {
+            *m.get_mut() += 1;
+        }
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Test(i32, u8);
+        let mut t = Test(1, 2);
+        increment(&mut t);
+        assert_eq!((get_i32(&t), get_i32(&mut t)), (2, 2));
+        let r = &t;
+        assert_eq!((*get!(r, i32), *get!(r, u8)), (2, 2));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_alias() {
+        #[derive(crate::Typemap)]
+        #[typemap(alias(Deps))]
+        struct Test<'a, T: Copy>([T; 1], &'a str);
+        #[derive(crate::Typemap)]
+        #[typemap_alias(Empty)]
+        struct Unit(#[typemap(skip)] ());
+        fn first<T: Copy>(deps: &impl Deps<'static, T>) -> T {
+            get!(deps, [T; 1])[0]
+        }
+        fn is_empty(_: &impl Empty) {}
+        #[derive(crate::Typemap)]
+        struct Larger(u8, [u8; 1], &'static str);
+        assert_eq!(first(&Larger(1, [2], "a")), 2);
+        assert_eq!(first(&Test([3], "b")), 3);
+        is_empty(&Unit(()));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn requires() {
+        fn sum(m: &requires!(i32, u8)) -> i32 {
+            *get!(m, i32) + i32::from(*get!(m, u8))
+        }
+        fn increment(m: &mut requires!(mut i32, u8)) {
+            *get_mut!(*m, i32) += 1;
+            *get_mut!(*m, u8) += 1;
+        }
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Test(i32, u8, f32);
+        let mut t = Test(1, 2, 3.0);
+        increment(&mut t);
+        assert_eq!(sum(&t), 5);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn get_many() {
+        #[derive(crate::Typemap)]
+        struct Test(i32, u8, &'static str);
+        let t = Test(1, 2, "a");
+        assert_eq!(get_many!(t, i32), (&1,));
+        assert_eq!(get_many!(t, &str, i32, u8,), (&"a", &1, &2));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_disjoint() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut, disjoint)]
+        struct Test<T>([T; 1], u8, #[typemap(readonly)] f32);
+        let mut t = Test([1], 2, 3.0);
+        let (array, byte) = get_disjoint_mut!(t, [i32; 1], u8);
+        array[0] += i32::from(*byte);
+        *byte += 1;
+        let (byte, array) = get_disjoint_mut!(t, u8, [i32; 1]);
+        *byte += array[0] as u8;
+        assert_eq!((t.0, t.1, t.2), ([3], 6, 3.0));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_query() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut, query)]
+        struct Test<T>(#[typemap(skip)] u16, [T; 1], u8, #[typemap(readonly)] f32);
+        let mut t = Test(0, [1], 2, 3.0);
+        let (float, array, byte) = query!(t, &f32, &mut [i32; 1], &mut u8);
+        array[0] += *float as i32;
+        *byte += 1;
+        let (byte,) = query!(t, &u8);
+        assert_eq!(*byte, 3);
+        assert_eq!((t.0, t.1, t.2, t.3), (0, [4], 3, 3.0));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "the fields of a query must not overlap")]
+    fn query_overlap() {
+        use crate::{QueryField, QueryFieldMut};
+        use core::ptr::addr_of_mut;
+        // an erroneous implementation giving two indices to the same field
+        struct Bytes([u8; 4]);
+        unsafe impl QueryField<[u8; 4]> for Bytes {
+            const INDEX: usize = 0;
+            unsafe fn field_ptr(map: *mut Self) -> *mut [u8; 4] {
+                unsafe { addr_of_mut!((*map).0) }
+            }
+        }
+        unsafe impl QueryFieldMut<[u8; 4]> for Bytes {}
+        unsafe impl QueryField<[u8; 2]> for Bytes {
+            const INDEX: usize = 1;
+            unsafe fn field_ptr(map: *mut Self) -> *mut [u8; 2] {
+                unsafe { addr_of_mut!((*map).0).cast() }
+            }
+        }
+        let mut b = Bytes([0; 4]);
+        query!(b, &mut [u8; 4], &[u8; 2]);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_visit() {
+        use crate::TypemapVisitor;
+        struct Size(usize);
+        impl<T> TypemapVisitor<T> for Size {
+            fn visit(&mut self, value: &T) {
+                self.0 += core::mem::size_of_val(value);
+            }
+        }
+        struct Sum(i32);
+        impl TypemapVisitor<i32> for Sum {
+            fn visit(&mut self, value: &i32) {
+                self.0 += value;
+            }
+        }
+        impl TypemapVisitor<u8> for Sum {
+            fn visit(&mut self, value: &u8) {
+                self.0 += i32::from(*value);
+            }
+        }
+        #[derive(crate::Typemap)]
+        #[typemap(visit)]
+        struct Test(
+            i32,
+            #[typemap(skip)] u64,
+            #[typemap(pad)] crate::CachePadded<u8>,
+        );
+        let t = Test(1, 2, crate::CachePadded::new(3));
+        let mut size = Size(0);
+        t.accept(&mut size);
+        assert_eq!((size.0, t.1), (5, 2));
+        let mut sum = Sum(0);
+        t.accept(&mut sum);
+        assert_eq!(sum.0, 4);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_dyn() {
+        trait Named {
+            fn name(&self) -> &'static str;
+        }
+        impl Named for i32 {
+            fn name(&self) -> &'static str {
+                "i32"
+            }
+        }
+        impl<T> Named for [T; 1] {
+            fn name(&self) -> &'static str {
+                "array"
+            }
+        }
+        #[derive(crate::Typemap)]
+        #[typemap(dyn(Named))]
+        #[typemap_dyn(core::fmt::Debug)]
+        struct Test<T>(i32, #[typemap(skip)] u8, [T; 1]);
+        let t = Test(1, 2, [3]);
+        let mut names = ["", ""];
+        let mut count = 0;
+        t.for_each_named(|field| {
+            names[count] = field.name();
+            count += 1;
+        });
+        assert_eq!(names, ["i32", "array"]);
+        t.for_each_debug(|_| count += 1);
+        assert_eq!((count, t.1), (4, 2));
+        let report = t.map_named(|field| field.name().len());
+        assert_eq!(report, [("i32", 3), ("[i32; 1]", 5)]);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_types() {
+        use crate::Typemap;
+        fn first<M: Typemap<Types = (A, B)>, A: Default, B>(_: &M) -> A {
+            A::default()
+        }
+        #[derive(crate::Typemap)]
+        #[typemap(types)]
+        struct Test<T>([T; 1], #[typemap(skip)] u8);
+        let types: <Test<i32> as Typemap>::Types = ([1], 2);
+        let t = Test(types.0, types.1);
+        assert_eq!((t.0, t.1), ([1], 2));
+        assert_eq!(first(&t), [0]);
+        assert_eq!(Test::<i32>::LEN, 2);
+        let mut names = Test::<i32>::type_names();
+        assert_eq!(names.len(), 2);
+        assert_eq!((names.next(), names.next()), (Some("[i32; 1]"), Some("u8")));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_split() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut, split)]
+        struct Test<T>([T; 1], #[typemap(readonly)] u8, f32);
+        #[derive(crate::Typemap)]
+        #[typemap(split)]
+        struct Single(#[typemap(mut)] i32, u8);
+        let mut t = Test([1], 2, 3.0);
+        let (array, float) = t.split_mut();
+        array[0] += 1;
+        *float += 1.0;
+        assert_eq!((t.0, t.1, t.2), ([2], 2, 4.0));
+        let mut s = Single(4, 5);
+        let (int,) = s.split_mut();
+        *int += 1;
+        assert_eq!((s.0, s.1), (5, 5));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_view() {
+        #[derive(crate::Typemap)]
+        #[typemap(view)]
+        struct Test<'a, T>(#[typemap(skip)] u8, [T; 1], &'a str);
+        #[derive(crate::Typemap)]
+        #[typemap(view)]
+        struct Named {
+            count: i32,
+            ratio: f32,
+        }
+        let t = Test(2, [1], "a");
+        let view: TestRef<'_, '_, i32> = t.as_view();
+        let copy = view;
+        assert_eq!(
+            (get!(view, [i32; 1]), *get!(copy, &str), t.0),
+            (&[1], "a", 2)
+        );
+        let n = Named {
+            count: 3,
+            ratio: 4.0,
+        };
+        let view = n.as_view();
+        assert_eq!((*get!(view, i32), *get!(view, f32)), (3, 4.0));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_view_mut() {
+        fn increment(mut view: TestMut<'_, i32>) {
+            get_mut!(view, [i32; 1])[0] += 1;
+            *get_mut!(view, f32) += f32::from(*get!(view, u8));
+        }
+        #[derive(crate::Typemap)]
+        #[typemap(mut, view_mut)]
+        struct Test<T>(
+            #[typemap(volatile)] u16,
+            [T; 1],
+            #[typemap(readonly)] u8,
+            f32,
+        );
+        let mut t = Test(0, [1], 2, 3.0);
+        increment(t.as_view_mut());
+        assert_eq!((t.0, t.1, t.2, t.3), (0, [2], 2, 5.0));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_typemap_readonly() {
+        #[derive(crate::Typemap)]
+        #[typemap_readonly]
+        struct Test(i32, #[typemap_mut] u8);
+        #[derive(crate::Typemap)]
+        #[typemap(readonly)]
+        struct Options(i32);
+        let mut t = Test(1, 2);
+        *get_mut!(t, u8) += 1;
+        assert_eq!((*get!(t, i32), *get!(t, u8)), (1, 3));
+        assert_eq!(*get!(Options(1), i32), 1);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_typemap_skip() {
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Test(i32, #[typemap_skip] i32, #[typemap_skip] u8);
+        let mut t = Test(1, 2, 3);
+        *get_mut!(t, i32) += 1;
+        assert_eq!((t.0, t.1, t.2), (2, 2, 3));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_flatten() {
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Inner(u32, f32);
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Outer<'a>(#[typemap(flatten)] Inner, i8, &'a str);
+        #[derive(crate::Typemap)]
+        struct ReadOnly(#[typemap_flatten] Outer<'static>, bool);
+
+        let mut o = Outer(Inner(1, 2.0), 3, "outer");
+        *get_mut!(o, u32) += 1;
+        *get_mut!(o, i8) += 1;
+        assert_eq!((*get!(o, u32), *get!(o, f32), *get!(o, i8)), (2, 2.0, 4));
+        let r = ReadOnly(Outer(Inner(1, 2.0), 3, "outer"), true);
+        assert_eq!(
+            (*get!(r, u32), *get!(r, &str), *get!(r, bool)),
+            (1, "outer", true)
+        );
+    }
+
+    #[test]
+    fn impl_typemap() {
+        struct A<T>(T);
+        struct Test(i32, f32, A<u32>);
+        impl_typemap!(Test, i32, f32, A<u32>);
+        struct Mutable(u8, u16);
+        impl_typemap!(mut Mutable, u8, u16,);
 
-pub use typemap_meta_derive::*;
+        let t = Test(1, 2.0, A(3));
+        assert_eq!(
+            (*get!(t, i32), *get!(t, f32), get!(t, A<u32>).0),
+            (1, 2.0, 3)
+        );
+        let mut m = Mutable(1, 2);
+        *get_mut!(m, u16) += 1;
+        assert_eq!((*get!(m, u8), *get!(m, u16)), (1, 3));
+    }
 
-/// Helper trait to get a specific type `T` from a tuple struct containing disjoint heterogeneous types
-pub trait Get<T> {
-    fn get(&self) -> &T;
-}
+    #[test]
+    fn impl_typemap_helpers() {
+        fn sum(m: &requires!(i32, u8)) -> i32 {
+            *get!(m, i32) + i32::from(*get!(m, u8))
+        }
+        struct Front(i32, u8, Option<u16>);
+        impl_typemap!(mut Front, i32, u8, Option<u16>);
+        struct Back(u8, f32);
+        impl_typemap!(mut Back, u8, f32);
 
-/// Helper trait to mutably get a specific type `T` from a tuple struct containing disjoint heterogeneous types
-pub trait GetMut<T> {
-    fn get_mut(&mut self) -> &mut T;
-}
+        let (mut front, mut back) = (Front(1, 2, None), Back(3, 4.0));
+        assert_eq!(sum(&front), 3);
+        set!(front, 5i32);
+        assert_eq!(replace!(front, Some(6u16)), None);
+        assert_eq!(get_cloned!(front, Option<u16>), Some(6));
+        swap!(front, back, u8);
+        assert_eq!(get_many!(front, i32, u8), (&5, &3));
+        assert_eq!((back.0, back.1), (2, 4.0));
+    }
 
-/// Convenience macro to get a specific type `$t` from a tuple struct `$s` containing disjoint heterogeneous types
-///
-/// Passing a value is fine, as [`get`] will add a reference to `$t` before calling [`Get`].
-#[macro_export]
-macro_rules! get {
-    ($s:expr, $t:ty) => {
-        $crate::Get::<$t>::get(&$s)
-    };
-}
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_custom_bounds() {
+        use crate::AnyTypemap;
+        #[derive(crate::Typemap)]
+        #[typemap(mut, any, bound = "T: 'static")]
+        struct Test<T>([T; 1], u8);
+        let mut t = Test([1u32], 2);
+        *t.lookup_mut::<u8>().unwrap() += 1;
+        assert_eq!(t.lookup::<[u32; 1]>(), Some(&[1]));
+        assert_eq!(*get!(t, u8), 3);
+    }
 
-/// Convenience macro to mutably get a specific type `$t` from a tuple struct `$s` containing disjoint heterogeneous types
-///
-/// Passing a value is fine, as [`get_mut`] will add a reference to `$t` before calling [`GetMut`].
-#[macro_export]
-macro_rules! get_mut {
-    ($s:expr, $t:ty) => {
-        $crate::GetMut::<$t>::get_mut(&mut $s)
-    };
-}
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_phantom_data() {
+        use core::marker::PhantomData;
+        #[derive(crate::Typemap)]
+        struct Test<T>(u32, PhantomData<T>, #[typemap(include)] PhantomData<u8>);
+        let t = Test::<i32>(1, PhantomData, PhantomData);
+        assert_eq!(*get!(t, u32), 1);
+        assert_eq!(*get!(t, PhantomData<u8>), PhantomData);
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::{get, Get, GetMut};
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_try_get() {
+        #[derive(Debug, Default, PartialEq)]
+        struct Limits(u32);
+        #[derive(crate::Typemap)]
+        struct Test<'l>(Option<Limits>, &'l i32);
+        let t = Test(Some(Limits(1)), &2);
+        assert_eq!(TryGet::<Limits>::try_get(&t), Some(&Limits(1)));
+        assert_eq!(TryGet::<&i32>::try_get(&t), Some(&&2));
 
-    // without using the generation macro
+        let limits = get_or_default!(t, Limits);
+        assert_eq!(*limits, Limits(1));
+        let t = Test(None, &2);
+        assert_eq!(*get_or_default!(t, Limits), Limits(0));
+        assert_eq!(get_or_default!(t, Limits).0, 0);
+
+        // the non-optional field takes precedence
+        #[derive(crate::Typemap)]
+        struct Both(Option<u8>, u8);
+        assert_eq!(TryGet::<u8>::try_get(&Both(None, 1)), Some(&1));
+    }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn impl_get() {
-        struct Test(i32, f32);
-        impl Get<i32> for Test {
-            fn get(&self) -> &i32 {
-                &self.0
-            }
+    fn derive_try_get_mut() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Test(Option<u32>, i32);
+        let mut t = Test(Some(1), 2);
+        if let Some(v) = TryGetMut::<u32>::try_get_mut(&mut t) {
+            *v += 1;
         }
-        impl Get<f32> for Test {
-            fn get(&self) -> &f32 {
-                &self.1
-            }
+        *TryGetMut::<i32>::try_get_mut(&mut t).unwrap() += 1;
+        assert_eq!(t.0, Some(2));
+        assert_eq!(t.1, 3);
+        let mut t = Test(None, 2);
+        assert_eq!(TryGetMut::<u32>::try_get_mut(&mut t), None);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn observe() {
+        extern crate std;
+        use crate::{Observed, Subscribers};
+        use std::cell::Cell;
+        std::thread_local! {
+            static CHANGES: Cell<u32> = const { Cell::new(0) };
         }
-        let t = Test(1, 2.0);
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Test(i32, f32);
+        #[derive(crate::Typemap, Default)]
+        #[typemap(mut)]
+        struct Registry(Subscribers<i32, 2>, Subscribers<f32>);
+        let mut t = Observed::new(Test(1, 2.0), Registry::default());
+        let count = |_: &i32| CHANGES.with(|c| c.set(c.get() + 1));
+        assert!(t.subscribe(count).is_ok());
+        assert!(t.subscribe(count).is_ok());
+        assert!(t.subscribe(count).is_err());
+
+        t.update(|v: &mut i32| *v += 1);
+        assert_eq!(CHANGES.with(Cell::get), 2);
+        t.update(|v: &mut f32| *v += 1.0);
+        assert_eq!(CHANGES.with(Cell::get), 2);
+        t.notify::<i32, 2>();
+        assert_eq!(CHANGES.with(Cell::get), 4);
+
+        let (t, _) = t.into_inner();
+        assert_eq!(*get!(t, i32), 2);
+        assert_eq!(*get!(t, f32), 3.0);
+    }
+
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn derive_freeze() {
+        #[derive(crate::Typemap)]
+        #[typemap(freeze)]
+        struct Test(i32, u8);
+        let frozen = Test(1, 2).freeze();
+        let shared = frozen.clone();
+        assert_eq!(*get!(shared, u8), 2);
+        let frozen = frozen.thaw().err().unwrap();
+        drop(shared);
+        let t = frozen.thaw().ok().unwrap();
         assert_eq!(*get!(t, i32), 1);
-        assert_eq!(*get!(t, f32), 2.0);
     }
 
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "alloc")]
     #[test]
-    fn impl_get_mut() {
-        struct Test(i32, f32);
-        impl Get<i32> for Test {
-            fn get(&self) -> &i32 {
-                &self.0
-            }
+    fn smart_pointers() {
+        extern crate alloc;
+        use alloc::{boxed::Box, rc::Rc, sync::Arc};
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Test(i32, u8);
+        let mut boxed = Box::new(Test(1, 2));
+        *get_mut!(boxed, i32) += 1;
+        let shared = Arc::new(Test(3, 4));
+        assert_eq!((*get!(boxed, i32), *get!(Rc::new(Test(5, 6)), u8)), (2, 6));
+        assert_eq!(*get!(Arc::clone(&shared), i32), 3);
+    }
+
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "embassy")]
+    #[test]
+    fn derive_mutex() {
+        use crate::MutexGet;
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embassy_sync::mutex::Mutex;
+        mod other {
+            pub struct Mutex<R, T>(pub R, pub T);
         }
-        impl Get<f32> for Test {
-            fn get(&self) -> &f32 {
-                &self.1
-            }
+        #[derive(crate::Typemap)]
+        struct Test(
+            embassy_sync::mutex::Mutex<NoopRawMutex, u32>,
+            #[typemap(embassy)] Mutex<NoopRawMutex, i8>,
+            other::Mutex<u8, u8>,
+        );
+        let t = Test(Mutex::new(1), Mutex::new(2), other::Mutex(3, 4));
+        futures::executor::block_on(async {
+            *lock!(t, u32).await += 1;
+            *MutexGet::<NoopRawMutex, i8>::lock(&t).await = 3;
+        });
+        assert_eq!(t.0.try_lock().map(|v| *v), Ok(2));
+        assert_eq!(t.1.try_lock().map(|v| *v), Ok(3));
+        assert_eq!(get!(t, other::Mutex<u8, u8>).1, 4);
+    }
+
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "rcu")]
+    #[test]
+    fn rcu() {
+        use crate::Publisher;
+        #[derive(crate::Typemap, Clone)]
+        struct Test(i32, u8);
+        let publisher = Publisher::new(Test(1, 2));
+        let reader = publisher.reader();
+        let before = reader.snapshot();
+        publisher.modify(|t| t.0 = 3);
+        assert_eq!(*get!(before, i32), 1);
+        assert_eq!(*get!(reader.snapshot(), i32), 3);
+        publisher.publish(Test(4, 5));
+        let after = reader.clone().snapshot();
+        assert_eq!((*get!(after, i32), *get!(after, u8)), (4, 5));
+        assert_eq!(*get!(publisher.snapshot(), u8), 5);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_commands() {
+        use crate::{Commands, Recorder};
+        #[derive(Debug, PartialEq, crate::Typemap)]
+        #[typemap(mut, commands(Debug, PartialEq))]
+        struct Test<'l>(&'l str, u32, #[typemap(skip)] f32);
+        let mut t = Test("a", 1, 2.0);
+        let mut last = None;
+        let mut recorder = Recorder::new(&mut t, |command: &TestCommand| {
+            last = Some(match command {
+                TestCommand::Field0(s) => TestCommand::Field0(s),
+                TestCommand::Field1(v) => TestCommand::Field1(*v),
+                TestCommand::__Marker(..) => unreachable!(),
+            })
+        });
+        recorder.set("b");
+        assert_eq!(*get!(recorder, &str), "b");
+        recorder.update(|v: &mut u32| *v += 1);
+        assert_eq!(last, Some(TestCommand::Field1(2)));
+        assert_eq!(t, Test("b", 2, 2.0));
+
+        t.apply(TestCommand::Field1(3));
+        assert_eq!(t, Test("b", 3, 2.0));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn migrate() {
+        #[derive(Debug, PartialEq, Default)]
+        struct Celsius(f32);
+        #[derive(Debug, PartialEq)]
+        struct Fahrenheit(f32);
+        fn to_celsius(f: Fahrenheit) -> Celsius {
+            Celsius((f.0 - 32.0) * 5.0 / 9.0)
         }
+        #[derive(crate::Typemap)]
+        struct MapV1(Fahrenheit, u8, bool);
+        #[derive(crate::Typemap)]
+        struct MapV2(u32, Celsius, i8, bool);
+        crate::migrate! {
+            MapV1(Fahrenheit, u8, bool) => MapV2(u32, Celsius, i8, bool);
+            convert Fahrenheit => Celsius with to_celsius;
+            convert u8 => u32;
+        }
+        let v2 = MapV2::from(MapV1(Fahrenheit(212.0), 3, true));
+        assert_eq!(*get!(v2, u32), 3);
+        assert_eq!(*get!(v2, Celsius), Celsius(100.0));
+        assert_eq!(*get!(v2, i8), 0);
+        assert!(*get!(v2, bool));
+    }
 
-        impl GetMut<i32> for Test {
-            fn get_mut(&mut self) -> &mut i32 {
-                &mut self.0
-            }
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_extract() {
+        extern crate std;
+        use std::{boxed::Box, string::String, string::ToString};
+        #[derive(crate::Typemap)]
+        struct Big(i32, String, u8, f32);
+        #[derive(crate::Typemap)]
+        #[typemap(extract)]
+        struct Small(
+            String,
+            #[typemap(boxed)] Box<u8>,
+            #[typemap(skip)] Option<i32>,
+        );
+        let big = Big(1, "a".to_string(), 2, 3.0);
+        let small = Small::extracted_from(&big);
+        assert_eq!(*get!(small, String), "a");
+        assert_eq!(*get!(small, u8), 2);
+        assert_eq!(small.2, None);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn borrow_map() {
+        struct Config {
+            scale: i32,
         }
-        impl GetMut<f32> for Test {
-            fn get_mut(&mut self) -> &mut f32 {
-                &mut self.1
-            }
+        let config = Config { scale: 2 };
+        let mut values = [1, 2];
+        let mut count = 0u8;
+        let mut map = crate::borrow_map!(&config: Config, &mut values: [i32; 2], &mut count: u8);
+        let scale = get!(map, Config).scale;
+        for value in get_mut!(map, [i32; 2]).iter_mut() {
+            *value *= scale;
+        }
+        *get_mut!(map, u8) += 1;
+        assert_eq!(*get!(map, [i32; 2]), [2, 4]);
+        assert_eq!(values, [2, 4]);
+        assert_eq!(count, 1);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn difference() {
+        #[derive(crate::Typemap)]
+        struct Context(i32, u8, f32, Option<u16>);
+        #[derive(crate::Typemap)]
+        struct Shared(u8, Option<u16>, bool);
+        crate::difference! {
+            #[typemap(mut)]
+            struct Local = Context(i32, u8, f32, Option<u16>) - Shared(u8, Option<u16>, bool);
+        }
+        let mut local = Local::from(Context(1, 2, 3.0, None));
+        *get_mut!(local, f32) = 4.0;
+        assert_eq!(*get!(local, i32), 1);
+        assert_eq!(*get!(local, f32), 4.0);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn typemap() {
+        crate::typemap! {
+            #[typemap(mut)]
+            struct Test { i32, Option<u8>, &'static str, }
+            fn new;
+        }
+        crate::typemap! {
+            pub(crate) struct Pair { [u8; 1], [u8; 2] }
+        }
+        let mut t = Test::new(1, None, "test");
+        *get_mut!(t, i32) += 1;
+        assert_eq!((*get!(t, i32), *get!(t, &str)), (2, "test"));
+        let p = Pair([1], [2, 3]);
+        assert_eq!(*get!(p, [u8; 2]), [2, 3]);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn get_layered() {
+        #[derive(crate::Typemap)]
+        struct Base(i32, u8, f32);
+        #[derive(crate::Typemap)]
+        struct Middle(u8, Option<bool>);
+        #[derive(crate::Typemap)]
+        struct Top(f32);
+        let (base, middle, top) = (Base(1, 2, 3.0), Middle(4, None), Top(5.0));
+        assert_eq!(*get_layered!(top, middle, base, f32), 5.0);
+        assert_eq!(*get_layered!(top, middle, base, u8), 4);
+        assert_eq!(*get_layered!(top, middle, base, i32), 1);
+        assert_eq!(*get_layered!(top, middle, Option<bool>), None);
+        assert_eq!(*get_layered!(base, i32), 1);
+
+        // generic layers are selected according to their bounds
+        fn unbounded<L>(layer: &L, base: &Base) -> u8 {
+            *get_layered!(layer, base, u8)
         }
+        fn bounded<L: Get<u8>>(layer: &L, base: &Base) -> u8 {
+            *get_layered!(layer, base, u8)
+        }
+        assert_eq!((unbounded(&middle, &base), bounded(&middle, &base)), (2, 4));
+    }
 
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_volatile() {
+        #[derive(crate::Typemap)]
+        struct Test(#[typemap_volatile] u32, f32);
         let t = Test(1, 2.0);
-        assert_eq!(*get!(t, i32), 1);
+        assert_eq!(read_volatile!(t, u32), 1);
         assert_eq!(*get!(t, f32), 2.0);
+    }
 
-        let mut t = t;
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_volatile_mut() {
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Test(#[typemap_volatile] u32, #[typemap_volatile] bool, f32);
+        let mut t = Test(1, false, 2.0);
+        assert_eq!(read_volatile!(t, u32), 1);
+        assert!(!read_volatile!(t, bool));
+
+        write_volatile!(t, u32, 3);
+        write_volatile!(t, bool, true);
+        *get_mut!(t, f32) = 4.0;
+
+        assert_eq!(read_volatile!(t, u32), 3);
+        assert!(read_volatile!(t, bool));
+        assert_eq!(*get!(t, f32), 4.0);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_bytes() {
+        #[derive(Debug, PartialEq, crate::Typemap)]
+        #[typemap_bytes]
+        struct Inner(i8, [u16; 2]);
+        #[derive(Debug, PartialEq, crate::Typemap)]
+        #[typemap_bytes]
+        struct Test(u32, f32, bool, Inner);
+        assert_eq!(Test::SIZE, 14);
+
+        let t = Test(1, 2.0, true, Inner(-1, [3, 4]));
+        let mut bytes = [0; Test::SIZE];
+        t.to_bytes(&mut bytes);
+        assert_eq!(bytes, [1, 0, 0, 0, 0, 0, 0, 64, 1, 255, 3, 0, 4, 0]);
+        assert_eq!(Test::from_bytes(&bytes), t);
+
+        #[derive(Debug, Default, PartialEq)]
+        struct Cache(u64);
+        #[derive(Debug, PartialEq, crate::Typemap)]
+        #[typemap_bytes]
+        struct Skipped<T>(
+            u8,
+            #[typemap(skip)] Cache,
+            u16,
+            core::marker::PhantomData<T>,
+        );
+        assert_eq!(Skipped::<char>::SIZE, 3);
+        let s = Skipped::<char>(1, Cache(2), 3, core::marker::PhantomData);
+        let mut bytes = [0; 3];
+        s.to_bytes(&mut bytes);
+        assert_eq!(bytes, [1, 3, 0]);
+        assert_eq!(
+            Skipped::<char>::from_bytes(&bytes),
+            Skipped(1, Cache(0), 3, core::marker::PhantomData)
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_sealed() {
+        #[derive(crate::Typemap)]
+        #[typemap(sealed)]
+        struct Test<'l>(&'l i32, f32);
+        let t = Test(&1, 2.0);
+        assert_eq!(**get!(t, &i32), 1);
+        assert_eq!(*get!(t, f32), 2.0);
+    }
 
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_sealed_mut() {
+        #[derive(crate::Typemap)]
+        #[typemap(sealed)]
+        #[typemap_mut]
+        struct Test(i32, f32);
+        let mut t = Test(1, 2.0);
         *get_mut!(t, i32) = 3;
         *get_mut!(t, f32) = 4.0;
         assert_eq!(*get!(t, i32), 3);
         assert_eq!(*get!(t, f32), 4.0);
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn impl_get_ref() {
-        struct Test<'a>(&'a i32, &'a f32);
-        impl<'a> Get<&'a i32> for Test<'a> {
-            fn get(&self) -> &&'a i32 {
-                &self.0
+    fn derive_mem_usage() {
+        struct A(u32);
+        impl MemUsage for A {
+            fn mem_usage(&self) -> usize {
+                self.0 as usize
             }
         }
-        impl<'a> Get<&'a f32> for Test<'a> {
-            fn get(&self) -> &&'a f32 {
-                &self.1
+        struct B;
+        impl MemUsage for B {
+            fn mem_usage(&self) -> usize {
+                2
             }
         }
-        let t = Test(&1, &2.0);
-        assert_eq!(**get!(t, &i32), 1);
-        assert_eq!(**get!(t, &f32), 2.0);
+        #[derive(crate::Typemap)]
+        #[typemap(mem_usage)]
+        struct Inner(B, f32);
+        #[derive(crate::Typemap)]
+        #[typemap(mem_usage)]
+        struct Test(A, i32, Inner);
+        let t = Test(A(10), 1, Inner(B, 2.0));
+        assert_eq!(t.mem_usage(), 12);
+        let breakdown = t.mem_usage_breakdown();
+        assert_eq!(breakdown[0].1, Some(10));
+        assert_eq!(breakdown[1], ("i32", None));
+        assert_eq!(breakdown[2].1, Some(2));
     }
 
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "manifest")]
     #[test]
-    fn impl_get_mut_ref() {
-        struct Test<'a>(&'a i32, &'a f32);
-        impl<'a> Get<&'a i32> for Test<'a> {
-            fn get(&self) -> &&'a i32 {
-                &self.0
-            }
-        }
-        impl<'a> Get<&'a f32> for Test<'a> {
-            fn get(&self) -> &&'a f32 {
-                &self.1
-            }
-        }
+    fn derive_manifest() {
+        #[derive(crate::Typemap)]
+        #[typemap_mut]
+        struct Test<'l>(&'l i32, #[typemap_volatile] u8, Option<[u8; 2]>);
+        assert_eq!(
+            Test::MANIFEST,
+            concat!(
+                r#"{"name":"Test","fields":["#,
+                r#"{"index":0,"type":"&'l i32","mutable":true,"volatile":false},"#,
+                r#"{"index":1,"type":"u8","mutable":true,"volatile":true},"#,
+                r#"{"index":2,"type":"Option<[u8;2]>","mutable":true,"volatile":false}"#,
+                r#"]}"#
+            )
+        );
+    }
 
-        impl<'a> GetMut<&'a i32> for Test<'a> {
-            fn get_mut(&mut self) -> &mut &'a i32 {
-                &mut self.0
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "serde")]
+    #[test]
+    fn derive_serde() {
+        extern crate std;
+        use std::string::{String, ToString};
+        mod as_string {
+            use serde::{Deserialize, Deserializer, Serializer};
+            pub fn serialize<S: Serializer>(value: &u8, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(value)
             }
-        }
-        impl<'a> GetMut<&'a f32> for Test<'a> {
-            fn get_mut(&mut self) -> &mut &'a f32 {
-                &mut self.1
+            pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+                let s = <&str>::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
             }
         }
-        let t = Test(&1, &2.0);
-        assert_eq!(**get!(t, &i32), 1);
-        assert_eq!(**get!(t, &f32), 2.0);
+        #[derive(crate::Typemap, Debug, PartialEq)]
+        #[typemap(serde)]
+        struct Test(
+            Option<i32>,
+            #[typemap(serde(rename = "small", with = "as_string"))] u8,
+            #[typemap(serde(skip))] String,
+        );
+        let t = Test(Some(1), 2, "skipped".to_string());
+        let json = serde_json::to_string(&t).unwrap();
+        assert_eq!(json, r#"{"Option<i32>":1,"small":"2"}"#);
+        let r: Test =
+            serde_json::from_str(r#"{"small":"3","unknown":0,"Option<i32>":null}"#).unwrap();
+        assert_eq!(r, Test(None, 3, String::new()));
+        assert_eq!(
+            serde_json::from_str::<Test>(r#"{"small":"3"}"#).unwrap(),
+            Test(None, 3, String::new())
+        );
+        assert!(serde_json::from_str::<Test>(r#"{"Option<i32>":1}"#).is_err());
+        assert_eq!(
+            serde_json::from_str::<Test>(r#"[4,"5"]"#).unwrap(),
+            Test(Some(4), 5, String::new())
+        );
 
-        let mut t = t;
-
-        *get_mut!(t, &i32) = &3;
-        *get_mut!(t, &f32) = &4.0;
-        assert_eq!(**get!(t, &i32), 3);
-        assert_eq!(**get!(t, &f32), 4.0);
+        #[derive(crate::Typemap, Debug, PartialEq)]
+        #[typemap(serde)]
+        struct Defaults(bool, #[typemap(serde(default))] u16);
+        assert_eq!(
+            serde_json::from_str::<Defaults>(r#"{"bool":true}"#).unwrap(),
+            Defaults(true, 0)
+        );
+        assert_eq!(
+            serde_json::from_str::<Defaults>(r#"[true]"#).unwrap(),
+            Defaults(true, 0)
+        );
+        assert!(serde_json::from_str::<Defaults>(r#"{"u16":1}"#).is_err());
     }
 
-    // with using the generation macro
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_options() {
+        #[derive(crate::Typemap)]
+        #[typemap(mut, mem_usage)]
+        struct Test(i32, #[typemap(volatile)] u8, #[typemap(skip)] f32);
+        let mut t = Test(1, 2, 3.0);
+        *get_mut!(t, i32) = 4;
+        write_volatile!(t, u8, 5);
+        assert_eq!(*get!(t, i32), 4);
+        assert_eq!(read_volatile!(t, u8), 5);
+        assert_eq!(t.2, 3.0);
+        assert_eq!(t.mem_usage(), 0);
+    }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn derive_scalar() {
+    fn derive_boxed() {
         extern crate std;
-        use std::marker::PhantomData;
-        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-        struct A<T> {
-            _f: PhantomData<T>,
-        }
+        use std::boxed::Box;
         #[derive(crate::Typemap)]
-        struct Test(i32, f32, A<u32>);
-        let a = A { _f: PhantomData };
-        let t = Test(1, 2.0, a);
+        #[typemap(mut)]
+        struct Test(i32, #[typemap(boxed)] Box<[f32; 64]>);
+        let mut t = Test(1, Box::new([0.0; 64]));
+        get_mut!(t, [f32; 64])[1] = 2.0;
         assert_eq!(*get!(t, i32), 1);
-        assert_eq!(*get!(t, f32), 2.0);
-        assert_eq!(*get!(t, A<u32>), a);
+        assert_eq!(get!(t, [f32; 64])[1], 2.0);
+
+        #[derive(crate::Typemap)]
+        #[typemap(mut, sealed)]
+        struct Sealed(#[typemap(boxed)] std::boxed::Box<i32>);
+        let mut t = Sealed(Box::new(1));
+        *get_mut!(t, i32) = 2;
+        assert_eq!(*get!(t, i32), 2);
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn derive_scalar_mut() {
-        extern crate std;
-        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-        struct A<T>(T);
+    fn derive_pad() {
         #[derive(crate::Typemap)]
-        #[typemap_mut]
-        struct Test(i32, f32, A<u32>);
-        let a = A(3);
-        let t = Test(1, 2.0, a);
-        assert_eq!(*get!(t, i32), 1);
-        assert_eq!(*get!(t, f32), 2.0);
-        assert_eq!(*get!(t, A<u32>), a);
+        #[typemap(mut)]
+        struct Test(
+            #[typemap(pad)] crate::CachePadded<u8>,
+            #[typemap(pad)] CachePadded<u16>,
+        );
+        use crate::CachePadded;
+        let mut t = Test(CachePadded::new(1), CachePadded::new(2));
+        *get_mut!(t, u8) = 3;
+        assert_eq!(*get!(t, u8), 3);
+        assert_eq!(*get!(t, u16), 2);
+        let first = get!(t, u8) as *const u8 as usize;
+        let second = get!(t, u16) as *const u16 as usize;
+        assert!(first.abs_diff(second) >= 64);
+    }
 
-        let mut t = t;
-        let b = A(6);
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_max_size() {
+        #[derive(crate::Typemap)]
+        #[typemap(max_size = 16)]
+        struct Test(
+            u64,
+            #[typemap(max_size = 4)] u32,
+            #[typemap(max_size = 4)] [u8; 4],
+        );
+        let t = Test(1, 2, [3; 4]);
+        assert_eq!(*get!(t, u32), 2);
 
-        *get_mut!(t, i32) = 4;
-        *get_mut!(t, f32) = 5.0;
-        *get_mut!(t, A<u32>) = b;
+        #[derive(crate::Typemap)]
+        #[typemap(max_size = 8, instantiate("Generic<u8>", "Generic<u32>"))]
+        struct Generic<T>(u32, [T; 1]);
+        let g = Generic(1, [2u8]);
+        assert_eq!(*get!(g, [u8; 1]), [2]);
+    }
 
-        assert_eq!(*get!(t, i32), 4);
-        assert_eq!(*get!(t, f32), 5.0);
-        assert_eq!(*get!(t, A<u32>), b);
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_fingerprint() {
+        #[derive(crate::Typemap)]
+        #[typemap(fingerprint)]
+        struct Test(i32, Option<[u8; 2]>, f32);
+        #[derive(crate::Typemap)]
+        #[typemap(fingerprint)]
+        struct Same(i32, Option<[u8; 2]>, f32);
+        #[derive(crate::Typemap)]
+        #[typemap(fingerprint)]
+        struct Reordered(Option<[u8; 2]>, i32, f32);
+        assert_eq!(Test::FINGERPRINT, Same::FINGERPRINT);
+        assert_ne!(Test::FINGERPRINT, Reordered::FINGERPRINT);
+        assert_eq!(Test::FINGERPRINT, 0x74b6_e966_9daf_2021);
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn derive_struct() {
-        #[derive(Debug, PartialEq)]
-        struct A {}
-        #[derive(Debug, PartialEq)]
-        struct B {}
+    fn derive_display() {
+        extern crate std;
+        use std::{boxed::Box, string::ToString};
+        struct Limits;
         #[derive(crate::Typemap)]
-        struct Test(A, B);
-        let t = Test(A {}, B {});
-        assert_eq!(*get!(t, A), A {});
+        #[typemap(display)]
+        struct Test(
+            i32,
+            #[typemap(skip)] u8,
+            #[typemap(boxed)] Box<f32>,
+            Limits,
+            #[typemap(volatile)] u16,
+        );
+        let t = Test(-1, 2, Box::new(0.5), Limits, 3);
+        assert_eq!(t.to_string(), "Test(i32: -1, f32: 0.5, Limits, u16)");
+        assert_eq!(t.1, 2);
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn derive_struct_mut() {
-        #[derive(Debug, PartialEq)]
-        struct A {
-            x: u32,
-        }
+    fn derive_cell() {
+        use core::cell::{Cell, RefCell};
+        #[derive(crate::Typemap)]
+        #[typemap(cell)]
+        struct Test<'a>(
+            i32,
+            #[typemap(refcell)] Option<&'a str>,
+            #[typemap(skip)] u8,
+        );
+        let c = TestCell::from(Test(1, None, 2));
+        Get::<Cell<i32>>::get(&c).set(3);
+        *Get::<RefCell<Option<&str>>>::get(&c).borrow_mut() = Some("name");
+        assert_eq!(c.get::<i32>(), 3);
+        assert_eq!(c.get::<Option<&str>>(), Some("name"));
+        c.set(4i32);
+        assert_eq!(c.replace::<Option<&str>>(None), Some("name"));
+        assert_eq!(crate::CellGet::<i32>::replace(&c, 5), 4);
+        let t = Test::from(c);
+        assert_eq!(t.0, 5);
+        assert_eq!(t.1, None);
+        assert_eq!(t.2, 2);
         #[derive(Debug, PartialEq)]
-        struct B {}
+        struct NotCopy(u8);
         #[derive(crate::Typemap)]
-        #[typemap_mut]
-        struct Test(A, B);
-        let t = Test(A { x: 1 }, B {});
-        assert_eq!(*get!(t, A), A { x: 1 });
-
-        let mut t = t;
-
-        get_mut!(t, A).x = 2;
-        assert_eq!(*get!(t, A), A { x: 2 });
+        #[typemap(cell)]
+        struct Owned(#[typemap(refcell)] NotCopy);
+        let c = OwnedCell::from(Owned(NotCopy(1)));
+        assert_eq!(c.replace(NotCopy(2)), NotCopy(1));
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn derive_ref() {
-        #[derive(Debug, PartialEq)]
-        struct A {}
-        #[derive(Debug, PartialEq)]
-        struct B {}
+    fn derive_ffi() {
+        use crate::{CachePadded, FfiSafe, FieldLayout};
         #[derive(crate::Typemap)]
-        struct Test<'l>(&'l A, &'l B, i32, f32);
-        let a = A {};
-        let b = B {};
-        let t = Test(&a, &b, 1, 2.0);
-        assert_eq!(**get!(t, &A), A {});
-        assert_eq!(**get!(t, &B), B {});
-        assert_eq!(*get!(t, i32), 1);
-        assert_eq!(*get!(t, f32), 2.0);
+        #[typemap(ffi, mut)]
+        #[repr(C)]
+        struct Inner(u16, [u8; 3]);
+        #[derive(crate::Typemap)]
+        #[typemap(ffi)]
+        #[repr(C)]
+        struct Test(u8, Inner, #[typemap(pad)] CachePadded<u64>);
+        fn assert_ffi_safe<T: FfiSafe>() {}
+        assert_ffi_safe::<Test>();
+        assert_eq!(
+            Inner::LAYOUT,
+            [
+                FieldLayout {
+                    name: "u16",
+                    offset: 0,
+                    size: 2,
+                    align: 2
+                },
+                FieldLayout {
+                    name: "[u8;3]",
+                    offset: 2,
+                    size: 3,
+                    align: 1
+                },
+            ]
+        );
+        assert_eq!(Test::LAYOUT[1].offset, 2);
+        assert_eq!(Test::LAYOUT[2].name, "CachePadded<u64>");
+        assert_eq!(Test::LAYOUT[2].offset, Test::LAYOUT[2].align);
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn derive_ref_mut() {
-        #[derive(Debug, PartialEq)]
-        struct A {
-            x: u32,
-        }
-        #[derive(Debug, PartialEq)]
-        struct B {}
+    fn derive_layout() {
+        // without repr(C), fields are reordered to minimize padding
         #[derive(crate::Typemap)]
-        #[typemap_mut]
-        struct Test<'l>(&'l A, &'l B, i32, f32);
-        let a = A { x: 1 };
-        let b = B {};
-        let t = Test(&a, &b, 1, 2.0);
-        assert_eq!(**get!(t, &A), A { x: 1 });
-        assert_eq!(**get!(t, &B), B {});
-        assert_eq!(*get!(t, i32), 1);
-        assert_eq!(*get!(t, f32), 2.0);
-
-        let mut t = t;
+        struct Test(u8, u64, i8, f64);
+        use core::mem::{align_of, size_of};
+        let fields = size_of::<u8>() + size_of::<u64>() + size_of::<i8>() + size_of::<f64>();
+        let align = align_of::<u64>().max(align_of::<f64>());
+        assert_eq!(size_of::<Test>(), fields.next_multiple_of(align));
+    }
 
-        *get_mut!(t, &A) = &A { x: 2 };
-        *get_mut!(t, &B) = &B {};
-        *get_mut!(t, i32) = 3;
-        *get_mut!(t, f32) = 4.0;
-        assert_eq!(**get!(t, &A), A { x: 2 });
-        assert_eq!(**get!(t, &B), B {});
-        assert_eq!(*get!(t, i32), 3);
-        assert_eq!(*get!(t, f32), 4.0);
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_reset() {
+        #[derive(crate::Typemap)]
+        #[typemap(reset)]
+        struct Test<'a>(
+            Option<i32>,
+            #[typemap(default = "Some(4)")] Option<u8>,
+            #[typemap(default = "&[1, 2]")] &'a [u8],
+            f32,
+        );
+        let mut t = Test(Some(1), Some(2), &[], 3.0);
+        t.clear_all();
+        assert_eq!(*get!(t, Option<i32>), None);
+        assert_eq!(*get!(t, Option<u8>), None);
+        assert_eq!(*get!(t, f32), 3.0);
+        t.reset();
+        assert_eq!(*get!(t, Option<u8>), Some(4));
+        assert_eq!(*get!(t, &[u8]), &[1, 2]);
+        assert_eq!(*get!(t, f32), 0.0);
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn derive_box_trait() {
+    fn derive_collections() {
         extern crate std;
-        use std::{boxed::Box, fmt::Debug};
-        // trait TA and struct A
-        trait TA {
-            fn value_a(&self) -> i32;
-        }
-        #[derive(Debug, PartialEq)]
-        struct A {
-            v: i32,
-        }
-        impl TA for A {
-            fn value_a(&self) -> i32 {
-                self.v
-            }
-        }
-        // trait TB and struct B
-        trait TB {
-            fn value_b(&self) -> f32;
-        }
-        #[derive(Debug, PartialEq)]
-        struct B {
-            v: f32,
-        }
-        impl TB for B {
-            fn value_b(&self) -> f32 {
-                self.v
-            }
+        use std::vec::Vec;
+        #[derive(crate::Typemap)]
+        #[typemap(mut)]
+        struct Test(Vec<i32>, heapless::Vec<u8, 2>, Vec<f32>);
+        let mut t = Test(Vec::new(), heapless::Vec::new(), Vec::new());
+        assert_eq!(push!(t, i32, 1), Ok(()));
+        assert_eq!(push!(t, u8, 2), Ok(()));
+        assert_eq!(push!(t, u8, 3), Ok(()));
+        assert_eq!(push!(t, u8, 4), Err(4));
+        get_all_mut!(t, u8)[0] = 5;
+        assert_eq!(get_all!(t, i32), &[1]);
+        assert_eq!(get_all!(t, u8), &[5, 3]);
+        assert!(get_all!(t, f32).is_empty());
+        assert_eq!(get!(t, Vec<i32>).len(), 1);
+
+        // only vectors with a heapless path are bounded collections
+        mod other {
+            pub struct Vec<T, U>(pub T, pub U);
         }
-        // instance and asserts
         #[derive(crate::Typemap)]
-        struct Test(Box<dyn TA>, Box<dyn TB>);
-        let t = Test(Box::new(A { v: 1 }), Box::new(B { v: 2.0 }));
-        assert_eq!(get!(t, Box<dyn TA>).value_a(), 1);
-        assert_eq!(get!(t, Box<dyn TB>).value_b(), 2.0);
+        struct Plain(other::Vec<u8, u16>);
+        let p = Plain(other::Vec(1, 2));
+        assert_eq!(get!(p, other::Vec<u8, u16>).1, 2);
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn derive_box_trait_mut() {
-        extern crate std;
-        use std::{boxed::Box, fmt::Debug};
-        // trait TA and struct A
-        trait TA {
-            fn value_a(&self) -> i32;
-            fn set_a(&mut self, a: i32);
-        }
-        #[derive(Debug, PartialEq)]
-        struct A {
-            v: i32,
-        }
-        impl TA for A {
-            fn value_a(&self) -> i32 {
-                self.v
-            }
-            fn set_a(&mut self, a: i32) {
-                self.v = a;
-            }
-        }
-        // trait TB and struct B
-        trait TB {
-            fn value_b(&self) -> f32;
-            fn set_b(&mut self, b: f32);
-        }
-        #[derive(Debug, PartialEq)]
-        struct B {
-            v: f32,
-        }
-        impl TB for B {
-            fn value_b(&self) -> f32 {
-                self.v
-            }
-            fn set_b(&mut self, b: f32) {
-                self.v = b;
+    fn derive_any() {
+        use crate::AnyTypemap;
+        use core::any::TypeId;
+        #[derive(crate::Typemap)]
+        #[typemap(any, mut)]
+        struct Test(i32, &'static str);
+        #[derive(crate::Typemap)]
+        #[typemap(any)]
+        struct Immutable(i32, #[typemap(pad)] crate::CachePadded<u8>);
+        let mut t = Test(1, "a");
+        *t.lookup_mut::<i32>().unwrap() = 2;
+        assert_eq!(t.lookup::<i32>(), Some(&2));
+        assert_eq!(t.lookup::<&str>(), Some(&"a"));
+        assert_eq!(t.lookup::<u8>(), None);
+        for field in t.iter_any_mut() {
+            if let Some(value) = field.downcast_mut::<i32>() {
+                *value += 1;
             }
         }
-        // instance and asserts
+        assert_eq!(t.lookup::<i32>(), Some(&3));
+        let mut i = Immutable(3, crate::CachePadded::new(4));
+        let mut fields = i.iter_any();
+        assert_eq!(fields.next().unwrap().downcast_ref::<i32>(), Some(&3));
+        assert_eq!(fields.next().unwrap().downcast_ref::<u8>(), Some(&4));
+        assert!(fields.next().is_none());
+        drop(fields);
+        assert_eq!(i.lookup::<i32>(), Some(&3));
+        assert_eq!(i.lookup::<u8>(), Some(&4));
+        assert_eq!(i.lookup_mut::<i32>(), None);
+        let ready = i.fold_fields(true, |ready, field| {
+            ready && field.downcast_ref::<i32>().is_none_or(|value| *value > 0)
+        });
+        assert!(ready);
+        assert_eq!(t.fold_fields(0, |count, _| count + 1), 2);
+        *t.get_any_mut(TypeId::of::<&str>())
+            .unwrap()
+            .downcast_mut::<&str>()
+            .unwrap() = "b";
+        assert_eq!(t.lookup::<&str>(), Some(&"b"));
+        assert!(t.get_any_mut(TypeId::of::<u8>()).is_none());
+        let field = i.get_any(TypeId::of::<u8>()).unwrap();
+        assert_eq!(field.downcast_ref::<u8>(), Some(&4));
+        assert!(i.get_any(TypeId::of::<&str>()).is_none());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_dyn_typemap() {
+        use crate::{AnyTypemap, DynTypemap};
+        use core::any::TypeId;
         #[derive(crate::Typemap)]
-        #[typemap_mut]
-        struct Test(Box<dyn TA>, Box<dyn TB>);
-        let t = Test(Box::new(A { v: 1 }), Box::new(B { v: 2.0 }));
-        assert_eq!(get!(t, Box<dyn TA>).value_a(), 1);
-        assert_eq!(get!(t, Box<dyn TB>).value_b(), 2.0);
+        #[typemap(any, mut)]
+        struct Test(i32, #[typemap(readonly)] &'static str);
+        #[derive(crate::Typemap)]
+        #[typemap(any)]
+        struct Immutable(#[typemap(pad)] crate::CachePadded<u8>);
+        let mut t = Test(1, "a");
+        let map: &mut dyn DynTypemap = &mut t;
+        assert_eq!(map.field_count(), 2);
+        assert_eq!(
+            (map.type_name(0), map.type_name(1), map.type_name(2)),
+            (Some("i32"), Some("&str"), None)
+        );
+        *map.lookup_mut::<i32>().unwrap() += 1;
+        assert!(map.lookup_mut::<&str>().is_none());
+        assert!(map.get_dyn(TypeId::of::<&str>()).is_some());
+        assert_eq!(t.0, 2);
+        let mut i = Immutable(crate::CachePadded::new(3));
+        let map: &mut dyn DynTypemap = &mut i;
+        assert_eq!((map.field_count(), map.type_name(0)), (1, Some("u8")));
+        assert_eq!(map.lookup::<u8>(), Some(&3));
+        assert!(map.get_dyn_mut(TypeId::of::<u8>()).is_none());
+    }
 
-        let mut t = t;
-        get_mut!(t, Box<dyn TA>).set_a(3);
-        get_mut!(t, Box<dyn TB>).set_b(4.0);
-        assert_eq!(get!(t, Box<dyn TA>).value_a(), 3);
-        assert_eq!(get!(t, Box<dyn TB>).value_b(), 4.0);
+    #[cfg(feature = "type-map")]
+    #[test]
+    fn any_type_map() {
+        use crate::AnyTypemap;
+        fn increment(map: &mut impl AnyTypemap) {
+            *map.lookup_mut::<i32>().unwrap() += 1;
+        }
+        let mut map = type_map::TypeMap::new();
+        map.insert(1i32);
+        increment(&mut map);
+        assert_eq!(map.lookup::<i32>(), Some(&2));
     }
 
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "type-map")]
     #[test]
-    fn derive_static_trait() {
+    fn derive_type_map() {
         extern crate std;
-        use std::fmt::Debug;
-        // trait TA and struct A
-        trait TA {
-            fn value_a(&self) -> i32;
+        use crate::AnyTypemap;
+        use std::boxed::Box;
+        #[derive(crate::Typemap)]
+        #[typemap(type_map)]
+        struct Test<T>(
+            [T; 1],
+            #[typemap(skip)] u8,
+            #[typemap(pad)] crate::CachePadded<u16>,
+            #[typemap(boxed)] Box<u32>,
+        );
+        let t = Test([1], 2, crate::CachePadded::new(3), Box::new(4));
+        assert_eq!(t.1, 2);
+        let map = t.into_type_map();
+        assert_eq!(map.lookup::<[i32; 1]>(), Some(&[1]));
+        assert_eq!(map.lookup::<u8>(), None);
+        assert_eq!(map.lookup::<u16>(), Some(&3));
+        assert_eq!(map.lookup::<u32>(), Some(&4));
+    }
+
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "bevy")]
+    #[test]
+    fn derive_bevy() {
+        use bevy_ecs::{resource::Resource, world::World};
+        #[derive(Resource, Clone, Debug, PartialEq)]
+        struct Score(u32);
+        #[derive(Resource, Clone, Debug, PartialEq)]
+        struct Level(u8);
+        #[derive(crate::Typemap, Debug, PartialEq)]
+        #[typemap(bevy)]
+        struct Test(Score, Level);
+        let mut world = World::new();
+        assert_eq!(Test::extract_from_world(&world), None);
+        Test(Score(1), Level(2)).insert_into_world(&mut world);
+        assert_eq!(world.resource::<Score>(), &Score(1));
+        assert_eq!(
+            Test::extract_from_world(&world),
+            Some(Test(Score(1), Level(2)))
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "frunk")]
+    #[test]
+    fn derive_frunk() {
+        use frunk_core::{hlist, hlist::Selector};
+        fn byte<M: Selector<u8, I>, I>(map: &mut M) -> u8 {
+            *map.get_mut() += 1;
+            *map.get()
         }
-        #[derive(Debug, PartialEq)]
-        struct A {
-            v: i32,
+        #[derive(crate::Typemap, Debug, PartialEq)]
+        #[typemap(frunk, mut)]
+        struct Test<T>(#[typemap(readonly)] [T; 1], u8, #[typemap(skip)] u16);
+        let mut t = Test::from_hlist(hlist![[1], 2, 3]);
+        assert_eq!(t, Test([1], 2, 3));
+        assert_eq!(byte(&mut t), 3);
+        let mut hlist = t.into_hlist();
+        assert_eq!(byte(&mut hlist), 4);
+        assert_eq!(hlist, hlist![[1], 4, 3]);
+    }
+
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn derive_rayon() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        trait Cache {
+            fn warm(&mut self);
+            fn size(&self) -> usize;
         }
-        impl TA for A {
-            fn value_a(&self) -> i32 {
-                self.v
+        impl<const N: usize> Cache for [u8; N] {
+            fn warm(&mut self) {
+                self.fill(1);
             }
-        }
-        // trait TB and struct B
-        trait TB {
-            fn value_b(&self) -> f32;
-        }
-        #[derive(Debug, PartialEq)]
-        struct B {
-            v: f32,
-        }
-        impl TB for B {
-            fn value_b(&self) -> f32 {
-                self.v
+            fn size(&self) -> usize {
+                self.iter().map(|&byte| usize::from(byte)).sum()
             }
         }
-        // instance and asserts
         #[derive(crate::Typemap)]
-        struct Test(&'static dyn TA, &'static dyn TB);
-        let a: &'static dyn TA = &A { v: 1 };
-        let b: &'static dyn TB = &B { v: 2.0 };
-        let t = Test(a, b);
-        assert_eq!(get!(t, &'static dyn TA).value_a(), 1);
-        assert_eq!(get!(t, &'static dyn TB).value_b(), 2.0);
+        #[typemap(mut, dyn(Cache), rayon)]
+        struct Test([u8; 1], [u8; 2], #[typemap(readonly)] [u8; 3]);
+        let mut t = Test([0], [0; 2], [1; 3]);
+        t.par_for_each_cache_mut(|cache| cache.warm());
+        let size = AtomicUsize::new(0);
+        t.par_for_each_cache(|cache| {
+            size.fetch_add(cache.size(), Ordering::Relaxed);
+        });
+        assert_eq!(size.into_inner(), 6);
     }
 
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "rhai")]
     #[test]
-    fn derive_static_trait_mut() {
+    fn derive_rhai() {
         extern crate std;
-        use std::fmt::Debug;
-        // trait TA and struct A
-        trait TA {
-            fn value_a(&self) -> i32;
-        }
-        #[derive(Debug, PartialEq)]
-        struct A {
-            v: i32,
-        }
-        impl TA for A {
-            fn value_a(&self) -> i32 {
-                self.v
+        use rhai::{Engine, Scope, INT};
+        #[derive(crate::Typemap, Clone)]
+        #[typemap(rhai, mut)]
+        struct Test(u8, f32, bool);
+        let mut engine = Engine::new();
+        Test::register_rhai(&mut engine);
+        let mut scope = Scope::new();
+        scope.push("map", Test(1, 2.0, false));
+        let sum: INT = engine
+            .eval_with_scope(&mut scope, r#"map["u8"] + 1"#)
+            .unwrap();
+        assert_eq!(sum, 2);
+        engine
+            .run_with_scope(
+                &mut scope,
+                r#"map["u8"] = 200; map["f32"] = 0.5; map["bool"] = true;"#,
+            )
+            .unwrap();
+        let map: Test = scope.get_value("map").unwrap();
+        assert_eq!((map.0, map.1, map.2), (200, 0.5, true));
+        assert!(engine
+            .run_with_scope(&mut scope, r#"map["u8"] = 300;"#)
+            .is_err());
+        assert!(engine
+            .run_with_scope(&mut scope, r#"map["bool"] = 1;"#)
+            .is_err());
+        assert!(engine.run_with_scope(&mut scope, r#"map["i32"]"#).is_err());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_atomic() {
+        use crate::CachePadded;
+        use core::sync::atomic::{AtomicI8, AtomicUsize, Ordering};
+        #[derive(crate::Typemap)]
+        struct Test(
+            #[typemap(atomic)] core::sync::atomic::AtomicU64,
+            #[typemap(atomic)] AtomicI8,
+            #[typemap(atomic, pad)] CachePadded<AtomicUsize>,
+            u64,
+        );
+        let t = Test(
+            0.into(),
+            AtomicI8::new(-1),
+            CachePadded::new(AtomicUsize::new(2)),
+            3,
+        );
+        store!(t, u64, 4);
+        store!(t, usize, 5, Ordering::Relaxed);
+        assert_eq!(load!(t, u64), 4);
+        assert_eq!(load!(t, i8), -1);
+        assert_eq!(load!(t, usize, Ordering::Relaxed), 5);
+        assert_eq!(*get!(t, u64), 3);
+        assert_eq!(get!(t, AtomicI8).load(Ordering::Relaxed), -1);
+    }
+
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "async-graphql")]
+    #[test]
+    fn graphql_context() {
+        use crate::TypemapContext;
+        use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema};
+        #[derive(crate::Typemap)]
+        struct Data(i32, bool);
+        struct Query;
+        #[Object]
+        impl Query {
+            async fn value(&self, ctx: &Context<'_>) -> i32 {
+                *ctx.get_typed::<Data, i32>()
+            }
+            async fn flag(&self, ctx: &Context<'_>) -> bool {
+                *get!(*ctx.typemap::<Data>(), bool)
             }
         }
-        // trait TB and struct B
-        trait TB {
-            fn value_b(&self) -> f32;
-        }
+        let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+            .data(Data(1, true))
+            .finish();
+        let response = futures::executor::block_on(schema.execute("{ value flag }"));
+        assert!(response.errors.is_empty());
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["value"], 1);
+        assert_eq!(data["flag"], true);
+    }
+
+    #[cfg(feature = "derive")]
+    #[cfg(feature = "env")]
+    #[test]
+    fn derive_env() {
+        extern crate std;
+        use crate::{EnvError, EnvVarError};
+        use std::string::String;
         #[derive(Debug, PartialEq)]
-        struct B {
-            v: f32,
+        struct Port(u16);
+        fn parse_port(value: &str) -> Result<Port, core::num::ParseIntError> {
+            value.parse().map(Port)
         }
-        impl TB for B {
-            fn value_b(&self) -> f32 {
-                self.v
+        #[derive(crate::Typemap, Debug, PartialEq)]
+        struct Config(
+            #[typemap(env = "TYPEMAP_TEST_URL")] String,
+            #[typemap(env = "TYPEMAP_TEST_PORT", parse_with = "parse_port")] Port,
+            #[typemap(env = "TYPEMAP_TEST_WORKERS")] u8,
+            bool,
+        );
+        let lookup = |vars: &'static [(&str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| *value)
             }
-        }
-        // instance and asserts
-        #[derive(crate::Typemap)]
-        #[typemap_mut]
-        struct Test(&'static dyn TA, &'static dyn TB);
-        let a: &'static dyn TA = &A { v: 1 };
-        let b: &'static dyn TB = &B { v: 2.0 };
-        let t = Test(a, b);
-        assert_eq!(get!(t, &'static dyn TA).value_a(), 1);
-        assert_eq!(get!(t, &'static dyn TB).value_b(), 2.0);
-
-        let mut t = t;
-        let c: &'static dyn TA = &A { v: 3 };
-        let d: &'static dyn TB = &B { v: 4.0 };
-
-        *get_mut!(t, &'static dyn TA) = c;
-        *get_mut!(t, &'static dyn TB) = d;
+        };
+        let error = Config::from_env_with(lookup(&[
+            ("TYPEMAP_TEST_PORT", "port"),
+            ("TYPEMAP_TEST_WORKERS", "4"),
+        ]))
+        .unwrap_err();
+        assert_eq!(error.0.len(), 2);
+        assert_eq!(error.0[0], EnvVarError::Missing("TYPEMAP_TEST_URL"));
+        assert!(matches!(
+            error.0[1],
+            EnvVarError::Invalid("TYPEMAP_TEST_PORT", _)
+        ));
+        assert_eq!(
+            Config::from_env_with(lookup(&[
+                ("TYPEMAP_TEST_URL", "db"),
+                ("TYPEMAP_TEST_PORT", "80"),
+                ("TYPEMAP_TEST_WORKERS", "4")
+            ])),
+            Ok(Config(String::from("db"), Port(80), 4, false))
+        );
+        // these variables are not set in the environment
+        assert_eq!(Config::from_env().unwrap_err().0.len(), 3);
+        let _: fn() -> Result<Config, EnvError> = Config::from_env;
+    }
 
-        assert_eq!(get!(t, &'static dyn TA).value_a(), 3);
-        assert_eq!(get!(t, &'static dyn TB).value_b(), 4.0);
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_crate_path() {
+        mod inner {
+            #[derive(crate::Typemap)]
+            #[typemap(crate = "crate", mut, sealed)]
+            pub struct Test(pub i32, pub f32);
+        }
+        let mut t = inner::Test(1, 2.0);
+        *get_mut!(t, f32) = 3.0;
+        assert_eq!(*get!(t, i32), 1);
+        assert_eq!(*get!(t, f32), 3.0);
     }
 }
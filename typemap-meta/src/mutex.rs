@@ -0,0 +1,22 @@
+//! Fields behind an `embassy-sync` mutex, locked by the type of their value.
+
+use core::future::Future;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+
+/// Helper trait to asynchronously lock a specific type `T` from a tuple struct containing a mutex of it
+///
+/// It is implemented by the derive macro for fields of type `Mutex<M, T>` of `embassy-sync`,
+/// `M` being the kind of raw mutex.
+pub trait MutexGet<M: RawMutex, T> {
+    fn mutex(&self) -> &Mutex<M, T>;
+
+    /// Wait until the mutex of `T` is unlocked, and lock it
+    fn lock<'a>(&'a self) -> impl Future<Output = MutexGuard<'a, M, T>>
+    where
+        M: 'a,
+        T: 'a,
+    {
+        self.mutex().lock()
+    }
+}
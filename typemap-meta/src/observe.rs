@@ -0,0 +1,102 @@
+//! Typed change notifications, without allocation.
+
+use crate::{Get, GetMut};
+
+/// Fixed-capacity list of callbacks to invoke when a value of type `T` changes
+///
+/// A registry of subscribers for several types is a typemap containing one
+/// `Subscribers<T>` per observed type `T`.
+pub struct Subscribers<T, const N: usize = 4> {
+    callbacks: [Option<fn(&T)>; N],
+}
+
+impl<T, const N: usize> Subscribers<T, N> {
+    /// Create an empty list
+    pub const fn new() -> Self {
+        Self {
+            callbacks: [None; N],
+        }
+    }
+
+    /// Add `callback` to the list, or return it if the list is full
+    pub fn subscribe(&mut self, callback: fn(&T)) -> Result<(), fn(&T)> {
+        match self.callbacks.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(callback);
+                Ok(())
+            }
+            None => Err(callback),
+        }
+    }
+
+    /// Invoke all callbacks, in subscription order, with `value`
+    pub fn notify(&self, value: &T) {
+        for callback in self.callbacks.iter().flatten() {
+            callback(value);
+        }
+    }
+}
+
+impl<T, const N: usize> Default for Subscribers<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A typemap `M` along with a typemap `R` of [`Subscribers`] to notify of its changes
+///
+/// The typemap can be read through [`Get`], but only mutated through [`update`](Observed::update),
+/// which notifies the subscribers of the mutated type.
+pub struct Observed<M, R> {
+    map: M,
+    subscribers: R,
+}
+
+impl<M, R> Observed<M, R> {
+    pub fn new(map: M, subscribers: R) -> Self {
+        Self { map, subscribers }
+    }
+
+    /// Return the observed typemap
+    pub fn map(&self) -> &M {
+        &self.map
+    }
+
+    /// Return the observed typemap and its subscribers
+    pub fn into_inner(self) -> (M, R) {
+        (self.map, self.subscribers)
+    }
+
+    /// Register `callback` to be invoked after each change of the field of type `T`, return it if there is no room left
+    pub fn subscribe<T, const N: usize>(&mut self, callback: fn(&T)) -> Result<(), fn(&T)>
+    where
+        R: GetMut<Subscribers<T, N>>,
+    {
+        self.subscribers.get_mut().subscribe(callback)
+    }
+
+    /// Invoke the callbacks subscribed to the field of type `T`
+    pub fn notify<T, const N: usize>(&self)
+    where
+        M: Get<T>,
+        R: Get<Subscribers<T, N>>,
+    {
+        Get::<Subscribers<T, N>>::get(&self.subscribers).notify(self.map.get());
+    }
+
+    /// Mutate the field of type `T` using `f`, and then invoke the callbacks subscribed to it
+    pub fn update<T, const N: usize>(&mut self, f: impl FnOnce(&mut T))
+    where
+        M: Get<T> + GetMut<T>,
+        R: Get<Subscribers<T, N>>,
+    {
+        f(self.map.get_mut());
+        Get::<Subscribers<T, N>>::get(&self.subscribers).notify(self.map.get());
+    }
+}
+
+impl<T, M: Get<T>, R> Get<T> for Observed<M, R> {
+    fn get(&self) -> &T {
+        self.map.get()
+    }
+}
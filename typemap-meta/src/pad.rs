@@ -0,0 +1,51 @@
+//! Cache-line padding, to avoid false sharing between fields.
+
+use core::ops::{Deref, DerefMut};
+
+/// Wrapper aligning and padding a value to the length of a cache line
+///
+/// Typemap fields declared as `CachePadded<T>` and marked with `#[typemap(pad)]`
+/// have their accessors keyed on `T`, so the padding is transparent to users of the map.
+/// The alignment is 128 bytes on `x86_64` and `aarch64`, where adjacent cache lines are
+/// prefetched together, and 64 bytes elsewhere.
+#[cfg_attr(
+    any(target_arch = "x86_64", target_arch = "aarch64"),
+    repr(C, align(128))
+)]
+#[cfg_attr(
+    not(any(target_arch = "x86_64", target_arch = "aarch64")),
+    repr(C, align(64))
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CachePadded<T>(pub T);
+
+impl<T> CachePadded<T> {
+    /// Pad `value`
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Return the padded value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for CachePadded<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
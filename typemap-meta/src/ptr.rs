@@ -0,0 +1,32 @@
+//! Access to typemaps owned by smart pointers.
+
+extern crate alloc;
+
+use crate::{Get, GetMut};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+
+impl<T, M: Get<T> + ?Sized> Get<T> for Box<M> {
+    fn get(&self) -> &T {
+        (**self).get()
+    }
+}
+
+impl<T, M: GetMut<T> + ?Sized> GetMut<T> for Box<M> {
+    fn get_mut(&mut self) -> &mut T {
+        (**self).get_mut()
+    }
+}
+
+impl<T, M: Get<T> + ?Sized> Get<T> for Rc<M> {
+    fn get(&self) -> &T {
+        (**self).get()
+    }
+}
+
+impl<T, M: Get<T> + ?Sized> Get<T> for Arc<M> {
+    fn get(&self) -> &T {
+        (**self).get()
+    }
+}
@@ -0,0 +1,157 @@
+//! Simultaneous access to several fields of a typemap, some of them mutably.
+//!
+//! The derive macro with `#[typemap(query)]` implements [`QueryField`] for the fields accessible
+//! by reference, giving each of them a distinct index. A tuple of shared and mutable references
+//! to field types implements [`Query`] when these indices are pairwise distinct, which is checked
+//! when the query is compiled. In debug builds, the fields fetched are also checked not to
+//! overlap in memory when the query is executed.
+
+use core::ops::Range;
+
+/// Raw access to the field of type `T` of a tuple struct containing disjoint heterogeneous types
+///
+/// # Safety
+///
+/// `INDEX` must differ between the implementations for a given struct, and
+/// [`field_ptr`](QueryField::field_ptr) must only access the field identified by `INDEX`.
+pub unsafe trait QueryField<T> {
+    /// Index of the field, distinct from those of the other field types
+    const INDEX: usize;
+
+    /// Get a pointer to the field of type `T`
+    ///
+    /// # Safety
+    ///
+    /// `map` must be valid for reads and writes.
+    unsafe fn field_ptr(map: *mut Self) -> *mut T;
+}
+
+/// Marker of the fields whose type `T` can be queried mutably
+///
+/// # Safety
+///
+/// The field of type `T` must be mutable.
+pub unsafe trait QueryFieldMut<T>: QueryField<T> {}
+
+/// Item of a query of typemap `M`, either `&T` or `&mut T`
+///
+/// # Safety
+///
+/// `INDEX` must be the index of the field accessed by [`fetch`](QueryItem::fetch).
+pub unsafe trait QueryItem<'a, M> {
+    /// Index of the field accessed
+    const INDEX: usize;
+
+    /// Borrow the field for the lifetime `'a`
+    ///
+    /// # Safety
+    ///
+    /// `map` must be valid and exclusively borrowed for `'a`, and no other item accessing the
+    /// same field may be fetched from it during `'a`.
+    unsafe fn fetch(map: *mut M) -> Self;
+
+    /// Address range of the field accessed, used in debug builds to check that the items of a
+    /// query do not overlap, as fields reached through `Deref` cannot be proven disjoint by their
+    /// indices only
+    ///
+    /// # Safety
+    ///
+    /// `map` must be valid for reads and writes.
+    unsafe fn address_range(map: *mut M) -> Range<usize>;
+}
+
+/// Address range of the value of type `T` at `ptr`
+fn address_range<T>(ptr: *mut T) -> Range<usize> {
+    let start = ptr as usize;
+    start..start + core::mem::size_of::<T>()
+}
+
+unsafe impl<'a, T: 'a, M: QueryField<T>> QueryItem<'a, M> for &'a T {
+    const INDEX: usize = M::INDEX;
+
+    unsafe fn fetch(map: *mut M) -> Self {
+        unsafe { &*M::field_ptr(map) }
+    }
+
+    unsafe fn address_range(map: *mut M) -> Range<usize> {
+        address_range(unsafe { M::field_ptr(map) })
+    }
+}
+
+unsafe impl<'a, T: 'a, M: QueryFieldMut<T>> QueryItem<'a, M> for &'a mut T {
+    const INDEX: usize = M::INDEX;
+
+    unsafe fn fetch(map: *mut M) -> Self {
+        unsafe { &mut *M::field_ptr(map) }
+    }
+
+    unsafe fn address_range(map: *mut M) -> Range<usize> {
+        address_range(unsafe { M::field_ptr(map) })
+    }
+}
+
+/// Helper trait to borrow several distinct types of a tuple struct containing disjoint heterogeneous types at the same time, some of them mutably
+///
+/// It is implemented for tuples of up to 8 [`QueryItem`]s. Requesting the same type twice fails
+/// to compile when the query is instantiated.
+pub trait Query<'a, M>: Sized {
+    fn query(map: &'a mut M) -> Self;
+}
+
+/// Whether all `indices` are distinct
+pub const fn distinct(indices: &[usize]) -> bool {
+    let mut i = 0;
+    while i < indices.len() {
+        let mut j = i + 1;
+        while j < indices.len() {
+            if indices[i] == indices[j] {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Whether all non-empty `ranges` are disjoint
+fn disjoint(ranges: &[Range<usize>]) -> bool {
+    ranges.iter().enumerate().all(|(i, a)| {
+        ranges[i + 1..]
+            .iter()
+            .all(|b| a.is_empty() || b.is_empty() || a.end <= b.start || b.end <= a.start)
+    })
+}
+
+macro_rules! impl_query {
+    ($($q:ident),+) => {
+        impl<'a, M, $($q: QueryItem<'a, M>),+> Query<'a, M> for ($($q,)+) {
+            fn query(map: &'a mut M) -> Self {
+                const {
+                    assert!(
+                        distinct(&[$($q::INDEX),+]),
+                        "the types of a query must be distinct"
+                    )
+                };
+                let map: *mut M = map;
+                #[cfg(debug_assertions)]
+                {
+                    // SAFETY: `map` is exclusively borrowed
+                    let ranges = unsafe { [$($q::address_range(map)),+] };
+                    assert!(disjoint(&ranges), "the fields of a query must not overlap");
+                }
+                // SAFETY: `map` is exclusively borrowed for `'a` and the items access distinct fields
+                unsafe { ($($q::fetch(map),)+) }
+            }
+        }
+    };
+}
+
+impl_query!(A);
+impl_query!(A, B);
+impl_query!(A, B, C);
+impl_query!(A, B, C, D);
+impl_query!(A, B, C, D, E);
+impl_query!(A, B, C, D, E, F);
+impl_query!(A, B, C, D, E, F, G);
+impl_query!(A, B, C, D, E, F, G, H);
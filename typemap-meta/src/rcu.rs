@@ -0,0 +1,100 @@
+//! Publication of immutable snapshots of a typemap, in the read-copy-update style.
+
+extern crate alloc;
+
+use crate::Get;
+use alloc::sync::Arc;
+use arc_swap::ArcSwap;
+use core::ops::Deref;
+
+/// Writer side of a typemap shared as immutable snapshots
+///
+/// Writers build a new typemap, or clone and modify the current one, and atomically publish it.
+/// Readers obtained with [`reader`](Publisher::reader) get consistent snapshots, which are not
+/// affected by later publications.
+pub struct Publisher<M> {
+    current: Arc<ArcSwap<M>>,
+}
+
+impl<M> Publisher<M> {
+    /// Create a publisher whose first snapshot is `map`
+    pub fn new(map: M) -> Self {
+        Self {
+            current: Arc::new(ArcSwap::from_pointee(map)),
+        }
+    }
+
+    /// Create a reader of the snapshots published by `self`
+    pub fn reader(&self) -> Reader<M> {
+        Reader {
+            current: self.current.clone(),
+        }
+    }
+
+    /// Get the latest published snapshot
+    pub fn snapshot(&self) -> Snapshot<M> {
+        Snapshot(self.current.load_full())
+    }
+
+    /// Replace the published snapshot with `map`
+    pub fn publish(&self, map: M) {
+        self.current.store(Arc::new(map));
+    }
+
+    /// Publish a modified clone of the latest snapshot
+    ///
+    /// If another writer publishes concurrently, `modify` is called again on its snapshot,
+    /// so that no publication is lost.
+    pub fn modify(&self, mut modify: impl FnMut(&mut M))
+    where
+        M: Clone,
+    {
+        self.current.rcu(|map| {
+            let mut map = M::clone(map);
+            modify(&mut map);
+            map
+        });
+    }
+}
+
+/// Reader side of a typemap shared as immutable snapshots, see [`Publisher`]
+pub struct Reader<M> {
+    current: Arc<ArcSwap<M>>,
+}
+
+impl<M> Reader<M> {
+    /// Get the latest published snapshot
+    pub fn snapshot(&self) -> Snapshot<M> {
+        Snapshot(self.current.load_full())
+    }
+}
+
+impl<M> Clone for Reader<M> {
+    fn clone(&self) -> Self {
+        Self {
+            current: self.current.clone(),
+        }
+    }
+}
+
+/// Immutable snapshot of a typemap, as published by a [`Publisher`]
+pub struct Snapshot<M>(Arc<M>);
+
+impl<M> Clone for Snapshot<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<M> Deref for Snapshot<M> {
+    type Target = M;
+    fn deref(&self) -> &M {
+        &self.0
+    }
+}
+
+impl<T, M: Get<T>> Get<T> for Snapshot<M> {
+    fn get(&self) -> &T {
+        self.0.get()
+    }
+}
@@ -0,0 +1,10 @@
+//! Visitation of all the fields of a typemap.
+
+/// Visitor of the fields of type `T` of typemaps
+///
+/// The derive macro with `#[typemap(visit)]` generates `accept`, which calls the visitor on all
+/// the fields accessible by reference, in declaration order. Visitors can be implemented for all
+/// the types satisfying some bounds, or for specific types only.
+pub trait TypemapVisitor<T: ?Sized> {
+    fn visit(&mut self, value: &T);
+}